@@ -0,0 +1,23 @@
+// One large opening on Easy, which runs for dozens of chunks, so the time
+// is mostly spent spreading the cascade. The seed, click and bench name
+// stay fixed so criterion can compare any two runs.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use minesweepfinity::{
+    aux::coord::Coord,
+    game::{Game, chunk::Difficulty},
+};
+
+fn open(seed: u64, difficulty: Difficulty, at: Coord<isize>) -> Game {
+    let mut game = Game::with_settings(seed, difficulty);
+    game.touch(&[at]);
+    while game.spread() {}
+    game
+}
+
+fn cascade(c: &mut Criterion) {
+    c.bench_function("cascade easy", |b| b.iter(|| open(7, Difficulty::Easy, Coord(0, 0))));
+}
+
+criterion_group!(benches, cascade);
+criterion_main!(benches);
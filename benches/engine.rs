@@ -0,0 +1,100 @@
+// Engine benchmarks. Every input comes from a fixed seed so results can
+// be compared between runs; criterion keeps the last run in target/criterion
+// and reports the change.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use minesweepfinity::{
+    aux::{coord::Coord, index_iter::IndexIterSigned},
+    game::{Game, AbsoluteCoord, chunk::{Chunk, Difficulty, Stage, field::BitField}},
+};
+
+use rand::{SeedableRng, XorShiftRng};
+
+const SEED: u64 = 7;
+
+fn rng() -> XorShiftRng {
+    XorShiftRng::from_seed([0x5eed, 1, 2, 3])
+}
+
+fn open(difficulty: Difficulty, at: Coord<isize>) -> Game {
+    let mut game = Game::with_settings(SEED, difficulty);
+    game.touch(&[at]);
+    while game.spread() {}
+    game
+}
+
+fn with_mines(c: &mut Criterion) {
+    for &difficulty in Difficulty::ALL.iter() {
+        let mut rng = rng();
+        c.bench_function(&format!("with_mines {}", difficulty.name()), move |b| {
+            b.iter(|| Chunk::with_mines(&mut rng, difficulty))
+        });
+    }
+}
+
+fn calc_neighbors(c: &mut Criterion) {
+    let mut game = open(Difficulty::Normal, Coord(4, 4));
+    let coord = Coord(0, 0);
+
+    let mines = game.chunks[&coord].mines;
+
+    // Includes putting back an uncounted chunk each time
+    c.bench_function("calc_neighbors", move |b| b.iter(|| {
+        let uncounted = Chunk::restore(Stage::Enmined, mines, BitField::default(), BitField::default(), Default::default());
+        game.chunks.insert(coord, uncounted);
+        game.calc_neighbors(black_box(coord));
+    }));
+}
+
+// Harder openings stay small. Easy ones run for dozens of chunks, and
+// are timed in benches/cascade.rs.
+fn cascade(c: &mut Criterion) {
+    for &difficulty in Difficulty::ALL.iter().filter(|&&difficulty| difficulty != Difficulty::Easy) {
+        c.bench_function(&format!("cascade {}", difficulty.name()), move |b| {
+            b.iter(|| open(difficulty, Coord(0, 0)))
+        });
+    }
+
+    c.bench_function("cascade lattice", |b| b.iter(|| {
+        let mut game = Game::with_settings(SEED, Difficulty::Normal);
+        for y in (-40..40).step_by(9) {
+            for x in (-40..40).step_by(9) {
+                game.touch(&[Coord(x, y)]);
+                while game.spread() {}
+            }
+        }
+        game
+    }));
+}
+
+fn coords(c: &mut Criterion) {
+    let coords: Vec<_> = IndexIterSigned::new(Coord(64, 64), Coord(-32, -32)).collect();
+
+    c.bench_function("AbsoluteCoord from world", |b| b.iter(|| {
+        black_box(&coords)
+            .iter()
+            .map(|&coord| AbsoluteCoord::from(coord).chunk)
+            .fold(Coord(0, 0), |acc, chunk| acc + chunk)
+    }));
+
+    let absolute: Vec<_> = coords.iter().map(|&coord| AbsoluteCoord::from(coord)).collect();
+    c.bench_function("AbsoluteCoord to world", |b| b.iter(|| {
+        black_box(&absolute)
+            .iter()
+            .map(|&AbsoluteCoord { chunk, square }| Coord::from(AbsoluteCoord { chunk, square }))
+            .fold(Coord(0, 0), |acc, coord| acc + coord)
+    }));
+
+    c.bench_function("IndexIterSigned 64x64", |b| b.iter(|| {
+        IndexIterSigned::new(black_box(Coord(64, 64)), Coord(-32, -32))
+            .fold(Coord(0, 0), |acc, coord| acc + coord)
+    }));
+
+    c.bench_function("IndexIterSigned self_and_adjacent", |b| b.iter(|| {
+        IndexIterSigned::self_and_adjacent(black_box(Coord::<isize>(5, -5)))
+            .fold(Coord(0, 0), |acc, coord| acc + coord)
+    }));
+}
+
+criterion_group!(benches, with_mines, calc_neighbors, cascade, coords);
+criterion_main!(benches);
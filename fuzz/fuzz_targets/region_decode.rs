@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minesweepfinity::game::region::{decode, encode};
+
+// Region slots come from disk, so the parser has to cope with anything.
+// Whatever it accepts must be exactly what the writer produces.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(Some(slot)) = decode(data) {
+        assert_eq!(&encode(slot.as_ref())[..], data);
+    }
+});
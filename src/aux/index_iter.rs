@@ -2,7 +2,7 @@
 use crate::aux::coord::Coord;
 // TODO make macro
 
-const CARDINAL_OFFSETS: &'static [Coord<isize>;4] = &[
+const CARDINAL_OFFSETS: &[Coord<isize>; 4] = &[
     Coord(-1, 0),
     Coord( 1, 0),
     Coord( 0,-1),
@@ -22,7 +22,7 @@ impl IndexIterSigned {
             count: 0,
             limit: (dimension.0 * dimension.1),
             dim_one: dimension.1,
-            offset: offset,
+            offset,
         }
     }
     
@@ -61,7 +61,7 @@ impl IndexIterUnsigned {
             count: 0,
             limit: (dimension.0 * dimension.1),
             dim_one: dimension.1,
-            offset: offset,
+            offset,
         }
     }
 }
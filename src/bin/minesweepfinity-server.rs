@@ -0,0 +1,61 @@
+use minesweepfinity::{
+    game::{Game, chunk::Difficulty},
+    net::{protocol::Mode, server::Server},
+};
+
+use std::{env, process};
+
+const USAGE: &str = "\
+Usage: minesweepfinity-server [--bind ADDRESS] [--seed NUMBER]
+                              [--difficulty easy|normal|hard] [--race]";
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+fn main() {
+    let (address, game, mode) = parse_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(1);
+    });
+
+    let mut server = Server::bind(&address, game).unwrap_or_else(|error| {
+        eprintln!("Cannot listen on {}: {}", address, error);
+        process::exit(1);
+    });
+    server.set_mode(mode);
+    if let Ok(address) = server.local_addr() {
+        eprintln!("Listening on {}", address);
+    }
+
+    if let Err(error) = server.run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> Result<(String, Game, Mode), String> {
+    let mut address = DEFAULT_ADDRESS.to_owned();
+    let mut seed = None;
+    let mut difficulty = Difficulty::default();
+    let mut mode = Mode::Cooperative;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--bind" => address = value()?,
+            "--seed" => {
+                let value = value()?;
+                seed = Some(value.parse().map_err(|_| format!("Invalid seed: {}", value))?);
+            },
+            "--difficulty" => {
+                let value = value()?;
+                difficulty = Difficulty::from_name(&value)
+                    .ok_or_else(|| format!("Invalid difficulty: {}", value))?;
+            },
+            "--race" => mode = Mode::Race,
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    Ok((address, Game::with_settings(seed.unwrap_or_else(rand::random), difficulty), mode))
+}
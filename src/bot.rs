@@ -0,0 +1,278 @@
+// Plays a game for programs rather than people, so solvers can be written
+// in any language and compared on the same seeds. Each line of input is a
+// JSON request, answered by one line of JSON.
+
+use crate::{
+    aux::coord::Coord,
+    game::{Game, AbsoluteCoord, SquareView, MAX_MOVE_DISTANCE, within_reach, chunk, event::Event},
+    net::protocol::view_char,
+};
+
+use serde_json::{json, Value};
+
+use std::{
+    convert::TryFrom,
+    io::{self, BufRead, Write},
+    sync::mpsc::Receiver,
+};
+
+// Most squares one `view` can ask for
+const MAX_VIEW_AREA: u64 = 256 * 256;
+
+pub struct Bot {
+    game: Game,
+    changes: Receiver<Event>,
+}
+
+impl Bot {
+    pub fn new(mut game: Game) -> Self {
+        let changes = game.subscribe();
+        Bot { game, changes }
+    }
+
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+
+    // Answers requests until the input runs out
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+
+            writeln!(output, "{}", self.respond(&line))?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    // Requests with an "id" get it back, to tell the answers apart
+    pub fn respond(&mut self, line: &str) -> Value {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(error) => return failure(format!("Invalid JSON: {}", error)),
+        };
+
+        let mut response = self.handle(&request)
+            .and_then(|response| match self.game.take_store_error() {
+                Some(error) => Err(format!("Cannot read paged-out chunks: {}", error)),
+                None => Ok(response),
+            })
+            .unwrap_or_else(failure);
+        if let Some(id) = request.get("id") {
+            response["id"] = id.clone();
+        }
+        response
+    }
+
+    fn handle(&mut self, request: &Value) -> Result<Value, String> {
+        let command = request.get("cmd").and_then(Value::as_str).ok_or("Missing cmd")?;
+
+        match command {
+            "status" => Ok(json!({
+                "ok": true,
+                "seed": self.game.seed(),
+                "difficulty": self.game.difficulty().name(),
+                "chunk_size": chunk::DIMENSION,
+                "allocated": self.game.allocated(),
+                "score": self.score(),
+            })),
+            "view" => self.view(request),
+            "reveal" | "flag" | "chord" => {
+                let at = coord(request)?;
+                if !within_reach(at) {
+                    return Err(format!("Moves must be at most {} squares from the origin", MAX_MOVE_DISTANCE));
+                }
+                Ok(self.act(command, at))
+            },
+            _ => Err(format!("Unknown cmd: {}", command)),
+        }
+    }
+
+    // The squares in a rectangle, as rows of the characters the network
+    // protocol uses
+    fn view(&mut self, request: &Value) -> Result<Value, String> {
+        let min = coord(request)?;
+        let (width, height) = (number(request, "width")?, number(request, "height")?);
+
+        let area = (width as u64).checked_mul(height as u64);
+        if width <= 0 || height <= 0 || area.filter(|&area| area <= MAX_VIEW_AREA).is_none() {
+            return Err(format!("Width and height must be positive and cover at most {} squares", MAX_VIEW_AREA));
+        }
+        let size = Coord(width as isize, height as isize);
+
+        // Views reaching past the ends of the coordinates can't be drawn
+        let far = |min: isize, side: isize| min.checked_add(side - 1);
+        let max = match (far(min.0, size.0), far(min.1, size.1)) {
+            (Some(x), Some(y)) => Coord(x, y),
+            _ => return Err("View reaches past the ends of the coordinates".to_owned()),
+        };
+
+        let first = AbsoluteCoord::from(min).chunk;
+        let last = AbsoluteCoord::from(max).chunk;
+        self.game.page_in(first, last + Coord::squared(1));
+
+        let rows: Vec<String> = (0..size.1)
+            .map(|y| (0..size.0).map(|x| view_char(self.square(min + Coord(x, y)))).collect())
+            .collect();
+
+        Ok(json!({ "ok": true, "rows": rows }))
+    }
+
+    // Plays a move to the end of its cascade, and reports what it did
+    fn act(&mut self, command: &str, at: Coord<isize>) -> Value {
+        let spreading = match command {
+            "reveal" => self.game.touch(&[at]),
+            "chord"  => self.game.chord(at),
+            _ => {
+                self.game.toggle_flag(at);
+                false
+            },
+        };
+        if spreading {
+            while self.game.spread() {}
+        }
+
+        let (mut revealed, mut won, mut lost) = (Vec::new(), Vec::new(), Vec::new());
+        for event in self.changes.try_iter() {
+            match event {
+                Event::SquareRevealed(Coord(x, y)) => revealed.push([x, y]),
+                Event::ChunkWon(Coord(x, y))  => won.push([x, y]),
+                Event::ChunkLost(Coord(x, y)) => lost.push([x, y]),
+                _ => (),
+            }
+        }
+
+        json!({
+            "ok": true,
+            "square": view_char(self.square(at)).to_string(),
+            "revealed": revealed,
+            "won": won,
+            "lost": lost,
+            "score": self.score(),
+        })
+    }
+
+    // Chunks that haven't been generated yet are all unclicked
+    fn square(&self, world_coord: Coord<isize>) -> SquareView {
+        let AbsoluteCoord { chunk, square } = world_coord.into();
+        self.game.get_chunk(chunk).map_or(SquareView::Unclicked, |chunk| chunk.view(square))
+    }
+
+    fn score(&self) -> Value {
+        json!({
+            "points": self.game.score(),
+            "won": self.game.chunks_won(),
+            "lost": self.game.chunks_lost(),
+        })
+    }
+}
+
+fn failure(error: String) -> Value {
+    json!({ "ok": false, "error": error })
+}
+
+fn number(request: &Value, key: &str) -> Result<i64, String> {
+    request.get(key)
+        .and_then(Value::as_i64)
+        .ok_or_else(|| format!("Missing or invalid {}", key))
+}
+
+fn coord(request: &Value) -> Result<Coord<isize>, String> {
+    let axis = |key| isize::try_from(number(request, key)?)
+        .map_err(|_| format!("{} is out of range", key));
+    Ok(Coord(axis("x")?, axis("y")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::chunk::Difficulty;
+
+    fn bot() -> Bot {
+        Bot::new(Game::with_settings(17, Difficulty::Normal))
+    }
+
+    fn rows(bot: &mut Bot, x: isize, y: isize, width: usize, height: usize) -> Vec<String> {
+        let request = json!({ "cmd": "view", "x": x, "y": y, "width": width, "height": height });
+        serde_json::from_value(bot.respond(&request.to_string())["rows"].clone()).unwrap()
+    }
+
+    #[test]
+    fn playing() {
+        let mut bot = bot();
+        assert_eq!(rows(&mut bot, -1, -1, 3, 2), ["###", "###"]);
+
+        // The first click always opens an area
+        let opened = bot.respond(r#"{"cmd": "reveal", "x": 3, "y": 3, "id": 1}"#);
+        assert_eq!(opened["ok"], true);
+        assert_eq!(opened["id"], 1);
+        assert_eq!(opened["square"], "0");
+        assert_eq!(opened["revealed"][0], json!([3, 3]));
+
+        let revealed = opened["revealed"].as_array().unwrap();
+        let view = rows(&mut bot, 0, 0, 8, 8);
+        let shown = view.iter().flat_map(|row| row.chars()).filter(|&ch| ch.is_ascii_digit()).count();
+        assert_eq!(shown, revealed.iter().filter(|at| at[0].as_i64() < Some(8) && at[1].as_i64() < Some(8)).count());
+
+        let flagged = bot.respond(r#"{"cmd": "flag", "x": -40, "y": 9}"#);
+        assert_eq!(flagged["square"], "f");
+        assert_eq!(rows(&mut bot, -40, 9, 1, 1), ["f"]);
+        assert_eq!(bot.respond(r#"{"cmd": "flag", "x": -40, "y": 9}"#)["square"], "#");
+
+        // Clicking a mine costs the chunk
+        let origin = &bot.game.chunks[&Coord(0, 0)];
+        let Coord(x, y) = Coord::<isize>::from(chunk::all_squares().find(|&square| origin.mines.get(square)).unwrap());
+        let exploded = bot.respond(&json!({ "cmd": "reveal", "x": x, "y": y }).to_string());
+        assert_eq!(exploded["square"], "*");
+        assert_eq!(exploded["lost"], json!([[0, 0]]));
+        assert_eq!(exploded["score"], json!({ "points": -5, "won": 0, "lost": 1 }));
+
+        let status = bot.respond(r#"{"cmd": "status"}"#);
+        assert_eq!(status["seed"], 17);
+        assert_eq!(status["difficulty"], "normal");
+        assert_eq!(status["score"]["lost"], 1);
+    }
+
+    #[test]
+    fn reproducible() {
+        let requests = [
+            r#"{"cmd": "reveal", "x": 0, "y": 0}"#,
+            r#"{"cmd": "chord", "x": 2, "y": 1}"#,
+            r#"{"cmd": "reveal", "x": -30, "y": 12}"#,
+            r#"{"cmd": "view", "x": -32, "y": -8, "width": 64, "height": 24}"#,
+        ];
+        let session = || {
+            let mut output = Vec::new();
+            bot().run(requests.join("\n").as_bytes(), &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = session();
+        assert_eq!(output.lines().count(), requests.len());
+        assert_eq!(output, session());
+    }
+
+    #[test]
+    fn bad_requests() {
+        let mut bot = bot();
+        for &line in [
+            "not json",
+            r#"{"x": 1, "y": 2}"#,
+            r#"{"cmd": "dance"}"#,
+            r#"{"cmd": "reveal", "x": 1}"#,
+            r#"{"cmd": "flag", "x": 1, "y": "two"}"#,
+            r#"{"cmd": "view", "x": 0, "y": 0, "width": 0, "height": 4}"#,
+            r#"{"cmd": "view", "x": 0, "y": 0, "width": 1000, "height": 1000}"#,
+            r#"{"cmd": "view", "x": 0, "y": 0, "width": 4611686018427387904, "height": 4}"#,
+            r#"{"cmd": "view", "x": 9223372036854775807, "y": 0, "width": 2, "height": 1}"#,
+            r#"{"cmd": "reveal", "x": 9223372036854775807, "y": 0}"#,
+            r#"{"cmd": "flag", "x": 0, "y": -9223372036854775808}"#,
+        ].iter() {
+            let response = bot.respond(line);
+            assert_eq!(response["ok"], false, "{}", line);
+            assert!(response["error"].is_string(), "{}", line);
+        }
+        assert_eq!(bot.game.allocated(), 0);
+    }
+}
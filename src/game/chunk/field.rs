@@ -45,6 +45,29 @@ impl BitField {
     pub fn get(&self, Coord(col, row): Coord<usize>) -> bool {
         self.0[row].get(col)
     }
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&BitRow(x)| x == 0)
+    }
+
+    pub fn to_bytes(self) -> [u8; DIMENSION] {
+        let mut bytes = [0; DIMENSION];
+        for (byte, row) in bytes.iter_mut().zip(self.0.iter()) { *byte = row.0; }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; DIMENSION]) -> Self {
+        let mut field = BitField::default();
+        for (row, &byte) in field.0.iter_mut().zip(bytes.iter()) { row.0 = byte; }
+        field
+    }
+}
+
+impl BitAnd for BitField {
+    type Output = Self;
+    fn bitand(mut self, other: Self) -> Self {
+        for (BitRow(x), BitRow(y)) in self.0.iter_mut().zip(other.0.iter()) { *x &= y; }
+        self
+    }
 }
 
 impl Not for BitField {
@@ -56,6 +79,7 @@ impl Not for BitField {
 }
 
 const NYBBLE: u32 = 0b1111;
+pub const NYBBLE_BYTES: usize = DIMENSION * 4;
 
 #[inline]
 fn nybble_shift(value: u8, index: usize) -> u32 {
@@ -100,4 +124,39 @@ impl NybbleField {
     pub fn get(&self, Coord(col, row): Coord<usize>) -> u8 {
         self.0[row].get(col)
     }
+
+    pub fn to_bytes(&self) -> [u8; NYBBLE_BYTES] {
+        let mut bytes = [0; NYBBLE_BYTES];
+        for (chunk, row) in bytes.chunks_mut(4).zip(self.0.iter()) {
+            chunk.copy_from_slice(&row.0.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; NYBBLE_BYTES]) -> Self {
+        let mut field = NybbleField::default();
+        for (row, chunk) in field.0.iter_mut().zip(bytes.chunks(4)) {
+            row.0 = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        field
+    }
+
+    // `planes[i]` has bit `i` of each value, one byte per row
+    pub fn from_planes(planes: [u64; 4]) -> Self {
+        let mut field = NybbleField::default();
+        for (index, row) in field.0.iter_mut().enumerate() {
+            for (bit, plane) in planes.iter().enumerate() {
+                row.0 |= spread((plane >> (index * DIMENSION)) as u8) << bit;
+            }
+        }
+        field
+    }
+}
+
+// Moves bit `n` to bit `4n`
+fn spread(byte: u8) -> u32 {
+    let value = u32::from(byte);
+    let value = (value | value << 12) & 0x000f_000f;
+    let value = (value | value <<  6) & 0x0303_0303;
+    (value | value << 3) & 0x1111_1111
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use super::{Chunk, Stage, Outcome, field::{BitField, DIMENSION}, neighbors::{self, Padded}};
+use crate::{
+    aux::coord::Coord,
+    game::SquareView,
+};
+
+pub const HALO_BYTES: usize = 5;
+
+// The mines just outside a chunk's edges, which is all it takes to
+// recount its neighbours without the surrounding chunks
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+pub struct Halo {
+    top: u8,
+    bottom: u8,
+    left: u8,
+    right: u8,
+    // Top left, top right, bottom left, bottom right
+    corners: u8,
+}
+
+impl Halo {
+    // `mines` gives the mines of the chunk at an offset from this one
+    pub fn gather(mines: impl Fn(Coord<isize>) -> BitField) -> Self {
+        const LAST: usize = DIMENSION - 1;
+        let column = |field: &[u8; DIMENSION], col: usize| field
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (row, byte)| acc | ((byte >> col) & 1) << row);
+
+        let above = mines(Coord( 0, -1)).to_bytes();
+        let below = mines(Coord( 0,  1)).to_bytes();
+        let corner = |offset, row: usize, col: usize| (mines(offset).to_bytes()[row] >> col) & 1;
+
+        Halo {
+            top: above[LAST],
+            bottom: below[0],
+            left: column(&mines(Coord(-1, 0)).to_bytes(), LAST),
+            right: column(&mines(Coord( 1, 0)).to_bytes(), 0),
+            corners:
+                corner(Coord(-1, -1), LAST, LAST)
+                | corner(Coord( 1, -1), LAST, 0) << 1
+                | corner(Coord(-1,  1), 0, LAST) << 2
+                | corner(Coord( 1,  1), 0, 0) << 3,
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; HALO_BYTES] {
+        [self.top, self.bottom, self.left, self.right, self.corners]
+    }
+
+    pub fn from_bytes(bytes: [u8; HALO_BYTES]) -> Self {
+        Halo { top: bytes[0], bottom: bytes[1], left: bytes[2], right: bytes[3], corners: bytes[4] }
+    }
+
+    // Rows of the chunk's mines with the halo around them, one bit per column
+    fn padded(self, mines: BitField) -> Padded {
+        let corner = |bit: u8| u16::from((self.corners >> bit) & 1);
+        let edge = |mask: u8, row: usize| u16::from((mask >> row) & 1);
+        let mut grid = [0; DIMENSION + 2];
+
+        grid[0] = corner(0) | u16::from(self.top) << 1 | corner(1) << 9;
+        for (row, &byte) in mines.to_bytes().iter().enumerate() {
+            grid[row + 1] = edge(self.left, row) | u16::from(byte) << 1 | edge(self.right, row) << 9;
+        }
+        grid[DIMENSION + 1] = corner(2) | u16::from(self.bottom) << 1 | corner(3) << 9;
+
+        grid
+    }
+}
+
+// A finished chunk, about half the size of a live one. Finished chunks
+// never change, so neighbour counts are recomputed when asked for.
+#[derive(Clone, Copy)]
+pub struct FrozenChunk {
+    pub outcome: Outcome,
+    pub mines: BitField,
+    pub clicked: BitField,
+    pub flags: BitField,
+    pub halo: Halo,
+}
+
+impl FrozenChunk {
+    pub fn freeze(chunk: &Chunk, halo: Halo) -> Self {
+        FrozenChunk {
+            outcome: chunk.outcome.expect("only finished chunks are frozen"),
+            mines: chunk.mines,
+            clicked: chunk.clicked,
+            flags: chunk.flags,
+            halo,
+        }
+    }
+
+    pub fn thaw(&self) -> Chunk {
+        Chunk {
+            stage: Stage::Neighbored,
+            outcome: Some(self.outcome),
+            mines: self.mines,
+            clicked: self.clicked,
+            flags: self.flags,
+            neighbors: neighbors::count(&self.halo.padded(self.mines)),
+        }
+    }
+
+    pub fn neighbors(&self, square: Coord<usize>) -> u8 {
+        count(&self.halo.padded(self.mines), square)
+    }
+
+    // Same as `Chunk::view` for the chunk this was frozen from
+    pub fn view(&self, square: Coord<usize>) -> SquareView {
+        if self.clicked.get(square) {
+            if self.mines.get(square) {
+                SquareView::Penalty
+            } else {
+                SquareView::Clicked(self.neighbors(square))
+            }
+        } else if self.outcome == Outcome::Won {
+            SquareView::Points
+        } else if self.mines.get(square) {
+            SquareView::Penalty
+        } else if self.flags.get(square) {
+            SquareView::Flagged
+        } else {
+            SquareView::Unclicked
+        }
+    }
+}
+
+fn count(grid: &Padded, Coord(col, row): Coord<usize>) -> u8 {
+    grid[row..row + 3]
+        .iter()
+        .map(|line| ((line >> col) & 0b111).count_ones() as u8)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, AbsoluteCoord, chunk::{all_squares, field::NybbleField}};
+    use std::mem::size_of;
+
+    #[test]
+    fn memory_per_chunk() {
+        assert_eq!(size_of::<FrozenChunk>(), 30);
+        assert!(size_of::<FrozenChunk>() * 2 <= size_of::<Chunk>());
+    }
+
+    #[test]
+    fn thaw_matches_live() {
+        let mut game = Game::with_settings(42, Default::default());
+        for x in (-40..40).step_by(9) {
+            game.touch(&[Coord(x, 5)]);
+        }
+
+        let live: Vec<_> = game.chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.stage() == Stage::Neighbored)
+            .map(|(&coord, _)| coord)
+            .collect();
+        assert!(!live.is_empty());
+
+        for coord in live {
+            let chunk = &game.chunks[&coord];
+            let halo = Halo::gather(|offset| game.mines(coord + offset).unwrap());
+            let mut finished = Chunk::restore(Stage::Enmined, chunk.mines, chunk.clicked, chunk.flags, NybbleField::default());
+            finished.set_neighbors(NybbleField::default());
+            finished.finish(Outcome::Lost);
+            let frozen = FrozenChunk::freeze(&finished, halo);
+            let thawed = frozen.thaw();
+
+            for square in all_squares() {
+                if chunk.mines.get(square) { continue; }
+                let world = Coord::from(AbsoluteCoord { chunk: coord, square });
+                assert_eq!(thawed.neighbors.get(square), chunk.neighbors.get(square), "{:?}", world);
+                assert_eq!(frozen.neighbors(square), chunk.neighbors.get(square), "{:?}", world);
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub mod field;
+pub mod frozen;
+pub mod neighbors;
 
-use rand::random;
+use rand::Rng;
 use crate::{
     aux::{index_iter, coord::Coord},
     game::SquareView,
@@ -11,26 +13,59 @@ use std::ops::Not;
 
 pub use self::field::DIMENSION;
 
-const MIN_MINES: u8 = 8;
-const MAX_MINES: u8 = 16;
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
 
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy   => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard   => "hard",
+        }
+    }
 
-#[derive(PartialEq, Clone, Copy)]
-pub enum Status {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Difficulty::ALL.iter().cloned().find(|difficulty| difficulty.name() == name)
+    }
+
+    // Half-open range of mines placed per chunk
+    fn mine_range(self) -> (u8, u8) {
+        match self {
+            Difficulty::Easy   => ( 4, 10),
+            Difficulty::Normal => ( 8, 16),
+            Difficulty::Hard   => (12, 20),
+        }
+    }
+}
+
+// How much of a chunk has been generated. Only moves forward, and
+// neighbours are only counted once a square in the chunk is clicked.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Stage {
+    #[default]
     Blank,
     Enmined,
     Neighbored,
-    Won,
-    Lost,
 }
 
-impl Default for Status {
-    fn default() -> Self { Status::Blank }
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Outcome {
+    Won,
+    Lost,
 }
 
 #[derive(Default)]
 pub struct Chunk {
-    pub status: Status,
+    stage: Stage,
+    outcome: Option<Outcome>,
     pub mines:     BitField,
     pub clicked:   BitField,
     pub flags:     BitField,
@@ -38,19 +73,64 @@ pub struct Chunk {
 }
 
 impl Chunk {
-    pub fn with_mines() -> Chunk {
-        let num_mines = random::<u8>() % (MAX_MINES - MIN_MINES) + MIN_MINES;
+    pub fn with_mines(rng: &mut impl Rng, difficulty: Difficulty) -> Chunk {
+        let (min_mines, max_mines) = difficulty.mine_range();
+        let num_mines = rng.gen_range(min_mines, max_mines);
         let mut mines = BitField::default();
 
-        for _ in 0..num_mines { mines.set(random_square()) }
+        for _ in 0..num_mines { mines.set(random_square(rng)) }
         
         Chunk {
-            status: Status::Enmined,
+            stage: Stage::Enmined,
             mines,
             ..Chunk::default()
         }
     }
     
+    // An unfinished chunk from its parts, such as one read back from storage
+    pub fn restore(stage: Stage, mines: BitField, clicked: BitField, flags: BitField, neighbors: NybbleField) -> Chunk {
+        Chunk { stage, outcome: None, mines, clicked, flags, neighbors }
+    }
+    
+    // A chunk that looks like `views`, for showing a game played elsewhere.
+    // Mines are only known where they show.
+    pub fn mirror(views: &[SquareView], outcome: Option<Outcome>) -> Chunk {
+        let mut chunk = Chunk { stage: Stage::Neighbored, outcome, ..Chunk::default() };
+        
+        for (square, view) in all_squares().zip(views) {
+            match *view {
+                SquareView::Clicked(count) => {
+                    chunk.clicked.set(square);
+                    chunk.neighbors.set(square, count);
+                },
+                SquareView::Penalty => {
+                    chunk.clicked.set(square);
+                    chunk.mines.set(square);
+                },
+                SquareView::Flagged => chunk.flags.set(square),
+                SquareView::Unclicked | SquareView::Points => (),
+            }
+        }
+        chunk
+    }
+    
+    pub fn stage(&self) -> Stage { self.stage }
+    pub fn outcome(&self) -> Option<Outcome> { self.outcome }
+    
+    pub fn set_neighbors(&mut self, neighbors: NybbleField) {
+        assert_eq!(self.stage, Stage::Enmined, "neighbours counted twice or without mines");
+        self.neighbors = neighbors;
+        self.stage = Stage::Neighbored;
+    }
+    
+    // Nothing can be won or lost before the counts are there to play with,
+    // and nothing finished can be won or lost again
+    pub fn finish(&mut self, outcome: Outcome) {
+        assert_eq!(self.stage, Stage::Neighbored, "chunk finished before its neighbours were counted");
+        assert_eq!(self.outcome, None, "chunk finished twice");
+        self.outcome = Some(outcome);
+    }
+    
     pub fn view(&self, square: Coord<usize>) -> SquareView {
         if self.clicked.get(square) {
             if self.mines.get(square) {
@@ -59,9 +139,9 @@ impl Chunk {
                 SquareView::Clicked(self.neighbors.get(square))
             }
         } else {
-            if self.status == Status::Won {
+            if self.outcome == Some(Outcome::Won) {
                 SquareView::Points
-            } else if self.status == Status::Lost && self.mines.get(square) {
+            } else if self.outcome == Some(Outcome::Lost) && self.mines.get(square) {
                 SquareView::Penalty
             } else if self.flags.get(square) {
                 SquareView::Flagged
@@ -83,20 +163,21 @@ pub fn all_squares() -> index_iter::IndexIterUnsigned {
     )
 }
 
-pub fn random_square() -> Coord<usize> {
+pub fn random_square(rng: &mut impl Rng) -> Coord<usize> {
     Coord(
-        random::<usize>() % DIMENSION,
-        random::<usize>() % DIMENSION,
+        rng.gen_range(0, DIMENSION),
+        rng.gen_range(0, DIMENSION),
     )
 }
     
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::XorShiftRng;
     
     #[test]
     fn test_neighbors_accessors() {
-        let mut chunk = Chunk::with_mines();
+        let mut chunk = Chunk::with_mines(&mut XorShiftRng::new_unseeded(), Difficulty::Normal);
 
         chunk.neighbors.set(Coord(0,7), 10);
         chunk.neighbors.set(Coord(0,6), 5);
@@ -0,0 +1,150 @@
+// Neighbour counts for a whole chunk at once. Each of the eight
+// directions gives a 64-bit mask of which squares have a mine that way,
+// and the masks are summed with a bit-sliced adder: `sum[i]` holds bit `i`
+// of every square's count.
+
+use super::field::{BitField, NybbleField, DIMENSION};
+
+// A chunk's mine rows with one square of its surroundings on every side.
+// Bit 0 of a row is the column left of the chunk.
+pub type Padded = [u16; DIMENSION + 2];
+
+// `surround` is the 3x3 block of chunks around this one, column by column
+// like `IndexIterSigned::self_and_adjacent`
+pub fn pad(surround: &[BitField; 9]) -> Padded {
+    const LAST: usize = DIMENSION - 1;
+    let rows = |chunk: usize| surround[chunk].to_bytes();
+    let (left, middle, right) = ([rows(0), rows(1), rows(2)], [rows(3), rows(4), rows(5)], [rows(6), rows(7), rows(8)]);
+
+    let line = |above: usize, row: usize| {
+        u16::from(left[above][row] >> LAST)
+            | u16::from(middle[above][row]) << 1
+            | u16::from(right[above][row] & 1) << 9
+    };
+
+    let mut grid = [0; DIMENSION + 2];
+    grid[0] = line(0, LAST);
+    for row in 0..DIMENSION {
+        grid[row + 1] = line(1, row);
+    }
+    grid[DIMENSION + 1] = line(2, 0);
+
+    grid
+}
+
+// Squares holding a mine count zero
+pub fn count(grid: &Padded) -> NybbleField {
+    // All ten rows at each horizontal offset, eight bits to a row
+    let mut shifted = [0u128; 3];
+    for (dx, rows) in shifted.iter_mut().enumerate() {
+        for (index, &line) in grid.iter().enumerate() {
+            *rows |= u128::from((line >> dx) as u8) << (index * DIMENSION);
+        }
+    }
+
+    let plane = |dx: usize, dy: usize| (shifted[dx] >> (dy * DIMENSION)) as u64;
+
+    let mut sum = [0u64; 4];
+    for dy in 0..3 {
+        for dx in 0..3 {
+            if (dx, dy) == (1, 1) { continue; }
+
+            let mut carry = plane(dx, dy);
+            for bit in sum.iter_mut() {
+                let next = *bit & carry;
+                *bit ^= carry;
+                carry = next;
+            }
+        }
+    }
+
+    let mines = plane(1, 1);
+    for bit in sum.iter_mut() {
+        *bit &= !mines;
+    }
+
+    NybbleField::from_planes(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aux::{coord::Coord, index_iter::IndexIterSigned},
+        game::chunk::{all_squares, Chunk, Difficulty},
+    };
+    use rand::{SeedableRng, XorShiftRng};
+
+    // The per-square count this replaced
+    fn reference(surround: &[BitField; 9]) -> NybbleField {
+        const DIMENSION_COORD: Coord<isize> = Coord::squared(DIMENSION as isize);
+        let mut canvas = NybbleField::default();
+
+        for square in all_squares() {
+            if surround[4].get(square) { continue; }
+
+            let count = IndexIterSigned::self_and_adjacent(Coord::<isize>::default())
+                .map(|offset| {
+                    let target = Coord::<isize>::from(square) + offset;
+                    let chunk = (target + DIMENSION_COORD) / DIMENSION_COORD;
+                    let square = (target + (Coord::squared(2) - chunk) * DIMENSION_COORD) % DIMENSION_COORD;
+                    ((chunk * Coord(3, 1)).sum() as usize, square.into())
+                })
+                .filter(|&(chunk, square)| surround[chunk].get(square))
+                .count();
+
+            canvas.set(square, count as u8);
+        }
+
+        canvas
+    }
+
+    // Sets a mine relative to the center chunk's top left square
+    fn set(surround: &mut [BitField; 9], target: Coord<isize>) {
+        const DIMENSION_COORD: Coord<isize> = Coord::squared(DIMENSION as isize);
+        let chunk = (target + DIMENSION_COORD) / DIMENSION_COORD;
+        let square = target + DIMENSION_COORD - chunk * DIMENSION_COORD;
+        surround[(chunk * Coord(3, 1)).sum() as usize].set(square.into());
+    }
+
+    fn assert_same(surround: &[BitField; 9]) {
+        let fast = count(&pad(surround));
+        let slow = reference(surround);
+        for square in all_squares() {
+            assert_eq!(fast.get(square), slow.get(square), "{:?}", square);
+        }
+    }
+
+    #[test]
+    fn every_window() {
+        // Each square's count only depends on the 3x3 window around it
+        for square in all_squares() {
+            let center = Coord::<isize>::from(square);
+
+            for pattern in 0..1 << 9 {
+                let mut surround = [BitField::default(); 9];
+                let window = IndexIterSigned::self_and_adjacent(center);
+                for (bit, target) in window.enumerate() {
+                    if pattern & 1 << bit != 0 { set(&mut surround, target); }
+                }
+                assert_same(&surround);
+            }
+        }
+    }
+
+    #[test]
+    fn random_surrounds() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        for &difficulty in Difficulty::ALL.iter() {
+            for _ in 0..2000 {
+                let mut surround = [BitField::default(); 9];
+                for field in surround.iter_mut() {
+                    *field = Chunk::with_mines(&mut rng, difficulty).mines;
+                }
+                assert_same(&surround);
+            }
+        }
+
+        assert_same(&[!BitField::default(); 9]);
+    }
+}
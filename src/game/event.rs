@@ -0,0 +1,34 @@
+use crate::aux::coord::Coord;
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Something that changed in a `Game`. Coordinates are in world space for
+// squares, and chunk space for chunks.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Event {
+    SquareRevealed(Coord<isize>),
+    FlagToggled(Coord<isize>),
+    ChunkAllocated(Coord<isize>),
+    ChunkWon(Coord<isize>),
+    ChunkLost(Coord<isize>),
+    ScoreChanged(i64),
+}
+
+// Everyone listening to a game. Events queue up until each subscriber
+// reads them, and subscribers that hang up are forgotten.
+#[derive(Default)]
+pub struct Observers {
+    subscribers: Vec<Sender<Event>>,
+}
+
+impl Observers {
+    pub fn subscribe(&mut self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        self.subscribers.retain(|subscriber| subscriber.send(event).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+// Generates chunks ahead of time on worker threads. A chunk's mines only
+// depend on the seed and its coordinate, so what the workers hand back is
+// the same whichever of them gets there first, and the same as what the
+// game would have generated itself.
+
+use super::{
+    chunk::{Chunk, Difficulty, neighbors, field::{BitField, NybbleField}},
+    chunk_rng,
+};
+use crate::aux::{coord::Coord, index_iter::IndexIterSigned};
+
+use std::{
+    sync::{Arc, Mutex, mpsc::{self, Receiver, Sender}},
+    thread::{self, JoinHandle},
+};
+
+// The mines of a chunk and its surroundings as first generated, and the
+// neighbour counts they give. The counts only hold while none of the
+// surrounding chunks has been generated differently since.
+pub struct Generated {
+    pub surround: [BitField; 9],
+    pub neighbors: NybbleField,
+}
+
+impl Generated {
+    pub fn mines(&self) -> BitField {
+        self.surround[4]
+    }
+}
+
+// Neighbouring chunks share most of their surrounds, so each chunk's mines
+// are generated once for the whole batch rather than once per chunk
+// around it
+struct Batch {
+    seed: u64,
+    difficulty: Difficulty,
+    mines: hashbrown::HashMap<Coord<isize>, BitField>,
+}
+
+impl Batch {
+    fn new(seed: u64, difficulty: Difficulty) -> Self {
+        Batch { seed, difficulty, mines: Default::default() }
+    }
+
+    fn generate(&mut self, coord: Coord<isize>) -> Generated {
+        let mut surround = [BitField::default(); 9];
+        for (mines, target) in surround.iter_mut().zip(IndexIterSigned::self_and_adjacent(coord)) {
+            let (seed, difficulty) = (self.seed, self.difficulty);
+            *mines = *self.mines
+                .entry(target)
+                .or_insert_with(|| Chunk::with_mines(&mut chunk_rng(seed, target), difficulty).mines);
+        }
+
+        Generated { surround, neighbors: neighbors::count(&neighbors::pad(&surround)) }
+    }
+}
+
+pub struct Generator {
+    jobs: Option<Sender<Vec<Coord<isize>>>>,
+    results: Receiver<(Coord<isize>, Generated)>,
+    workers: Vec<JoinHandle<()>>,
+    requested: hashbrown::HashSet<Coord<isize>>,
+}
+
+impl Generator {
+    pub fn new(seed: u64, difficulty: Difficulty, workers: usize) -> Self {
+        let (jobs, job_queue) = mpsc::channel::<Vec<Coord<isize>>>();
+        let (result_sender, results) = mpsc::channel();
+        let job_queue = Arc::new(Mutex::new(job_queue));
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let job_queue = Arc::clone(&job_queue);
+                let result_sender = result_sender.clone();
+
+                thread::spawn(move || loop {
+                    let job = job_queue.lock().unwrap().recv();
+                    let coords = match job {
+                        Ok(coords) => coords,
+                        Err(_) => return,
+                    };
+                    let mut batch = Batch::new(seed, difficulty);
+                    for coord in coords {
+                        if result_sender.send((coord, batch.generate(coord))).is_err() { return; }
+                    }
+                })
+            })
+            .collect();
+
+        Generator { jobs: Some(jobs), results, workers, requested: Default::default() }
+    }
+
+    // Splits the chunks not already asked for into one run per worker, so
+    // each worker shares mines between the chunks of its run
+    pub fn request(&mut self, coords: impl IntoIterator<Item=Coord<isize>>) {
+        let requested = &mut self.requested;
+        let fresh: Vec<_> = coords.into_iter().filter(|&coord| requested.insert(coord)).collect();
+        if fresh.is_empty() { return; }
+
+        if let Some(jobs) = &self.jobs {
+            for run in fresh.chunks(fresh.len().div_ceil(self.workers.len())) {
+                let _ = jobs.send(run.to_vec());
+            }
+        }
+    }
+
+    // Whatever the workers have finished, without waiting for the rest
+    pub fn finished<'a>(&'a mut self) -> impl Iterator<Item=(Coord<isize>, Generated)> + 'a {
+        let requested = &mut self.requested;
+        self.results.try_iter().inspect(move |(coord, _)| { requested.remove(coord); })
+    }
+
+    pub fn busy(&self) -> bool {
+        !self.requested.is_empty()
+    }
+}
+
+impl Drop for Generator {
+    fn drop(&mut self) {
+        // Closing the queue lets the workers run out of jobs and stop
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
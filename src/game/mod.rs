@@ -1,19 +1,47 @@
 pub mod chunk;
+pub mod region;
+pub mod generator;
+pub mod event;
+#[cfg(test)]
+mod properties;
 
-use self::chunk::{Chunk, field::NybbleField};
+use self::{
+    chunk::{Chunk, Difficulty, Stage, Outcome, neighbors, field::BitField, frozen::{FrozenChunk, Halo}},
+    region::{RegionStore, Slot, World},
+    generator::{Generator, Generated},
+    event::{Event, Observers},
+};
 use crate::aux::{
     index_iter::IndexIterSigned,
     coord::Coord,
     ModuloSignedExt,
     DivFloorSignedExt,
-    OptionalizeExt,
 };
 
-use std::ops::{Add, Sub, Mul, Div, Rem};
+use rand::{SeedableRng, XorShiftRng};
+
+use std::{
+    collections::BTreeMap,
+    io,
+    mem,
+};
+
+// Whoever is playing, in games with several players
+pub type PlayerId = u32;
+
+// Furthest from the origin a move can be made, leaving the rest of the
+// coordinates for its cascade to spread into
+pub const MAX_MOVE_DISTANCE: isize = isize::MAX / 2;
+
+pub fn within_reach(Coord(x, y): Coord<isize>) -> bool {
+    let reach = -MAX_MOVE_DISTANCE..=MAX_MOVE_DISTANCE;
+    reach.contains(&x) && reach.contains(&y)
+}
 
 type Board = hashbrown::HashMap<Coord<isize>, Chunk>;
+type FrozenBoard = hashbrown::HashMap<Coord<isize>, FrozenChunk>;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum SquareView {
     Clicked(u8),
     Unclicked,
@@ -22,6 +50,40 @@ pub enum SquareView {
     Points,
 }
 
+#[derive(Clone, Copy)]
+pub enum ChunkRef<'a> {
+    Live(&'a Chunk),
+    Frozen(&'a FrozenChunk),
+}
+
+impl<'a> ChunkRef<'a> {
+    pub fn view(self, square: Coord<usize>) -> SquareView {
+        match self {
+            ChunkRef::Live(chunk) => chunk.view(square),
+            ChunkRef::Frozen(chunk) => chunk.view(square),
+        }
+    }
+
+    pub fn stage(self) -> Stage {
+        match self {
+            ChunkRef::Live(chunk) => chunk.stage(),
+            ChunkRef::Frozen(_) => Stage::Neighbored,
+        }
+    }
+
+    pub fn outcome(self) -> Option<Outcome> {
+        match self {
+            ChunkRef::Live(chunk) => chunk.outcome(),
+            ChunkRef::Frozen(chunk) => Some(chunk.outcome),
+        }
+    }
+
+    // Being played, as opposed to just generated or already finished
+    pub fn unfinished(self) -> bool {
+        self.stage() == Stage::Neighbored && self.outcome().is_none()
+    }
+}
+
 pub struct AbsoluteCoord {
     pub chunk: Coord<isize>,
     pub square: Coord<usize>,
@@ -36,176 +98,604 @@ impl From<AbsoluteCoord> for Coord<isize> {
 impl From<Coord<isize>> for AbsoluteCoord {
     fn from(source: Coord<isize>) -> Self {
         AbsoluteCoord {
-            chunk:  source.map(|x| x.div_floor(chunk::DIMENSION as isize)),
+            chunk:  source.map(|x| DivFloorSignedExt::div_floor(x, chunk::DIMENSION as isize)),
             square: source.map(|x| x.modulo   (chunk::DIMENSION as isize)).into(),
         }       
     }
 }
 
+const WIN_POINTS:   i64 = 10;
+const LOSS_PENALTY: i64 = 5;
+
+pub fn points(won: u64, lost: u64) -> i64 {
+    won as i64 * WIN_POINTS - lost as i64 * LOSS_PENALTY
+}
+
 #[derive(Default)]
 pub struct Game {
     pub chunks: Board,
+    // Won and lost chunks, kept compact since they're mostly just looked at
+    pub frozen: FrozenBoard,
+    seed: u64,
+    difficulty: Difficulty,
     chunks_won: u64,
     chunks_lost: u64,
+    last_explosion: Option<Coord<isize>>,
+    bookmarks: BTreeMap<String, Coord<isize>>,
+    store: Option<RegionStore>,
+    cache_limit: usize,
+    // The last time a paged-out chunk couldn't be read back
+    store_error: Option<io::Error>,
+    // Blank squares revealed by the cascade's latest wave, and the previous
+    // wave's while it spreads. Kept around so cascades don't allocate.
+    wave: Vec<Coord<isize>>,
+    fringe: Vec<Coord<isize>>,
+    generator: Option<Generator>,
+    pregenerated: hashbrown::HashMap<Coord<isize>, Generated>,
+    // Worker counts for the chunk just clicked, until `calc_neighbors` runs
+    counted: Option<(Coord<isize>, Generated)>,
+    // Whether the first click has been made
+    opened: bool,
+    observers: Observers,
+    // Who won or lost each chunk, when anyone was acting
+    owners: hashbrown::HashMap<Coord<isize>, PlayerId>,
+    player: Option<PlayerId>,
 }
 
     
 impl Game {
-    pub fn new() -> Self { Game::default() }
+    pub fn with_settings(seed: u64, difficulty: Difficulty) -> Self {
+        Game { seed, difficulty, ..Game::default() }
+    }
+    
+    // Keep at most `cache_limit` chunks in memory, paging the rest to
+    // `store`. A store saved by an earlier game carries on from where that
+    // game left off, as long as it's the same world.
+    pub fn set_paging(&mut self, store: RegionStore, cache_limit: usize) -> io::Result<()> {
+        if let Some(world) = store.world() {
+            if (world.seed, world.difficulty) != (self.seed, self.difficulty) {
+                let message = format!("it holds the {} world with seed {}", world.difficulty.name(), world.seed);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            }
+            self.chunks_won = world.chunks_won;
+            self.chunks_lost = world.chunks_lost;
+            self.opened = world.opened;
+            self.bookmarks = world.bookmarks.clone();
+        }
+        
+        self.store = Some(store);
+        self.cache_limit = cache_limit;
+        Ok(())
+    }
+    
+    // Pages every chunk out, along with what it takes to open the store
+    // again later. Temporary stores go away anyway, so they're left be.
+    pub fn save(&mut self) -> io::Result<()> {
+        let store = match self.store.as_mut() {
+            Some(store) if !store.is_temporary() => store,
+            _ => return Ok(()),
+        };
+        
+        for (&coord, chunk) in self.chunks.iter() {
+            store.save(coord, ChunkRef::Live(chunk))?;
+        }
+        for (&coord, chunk) in self.frozen.iter() {
+            store.save(coord, ChunkRef::Frozen(chunk))?;
+        }
+        self.chunks.clear();
+        self.frozen.clear();
+        
+        store.save_world(World {
+            seed: self.seed,
+            difficulty: self.difficulty,
+            chunks_won: self.chunks_won,
+            chunks_lost: self.chunks_lost,
+            opened: self.opened,
+            bookmarks: self.bookmarks.clone(),
+        })
+    }
+    
+    // Generate chunks on `workers` background threads, ahead of `pregenerate`
+    pub fn set_generator(&mut self, workers: usize) {
+        self.generator = Some(Generator::new(self.seed, self.difficulty, workers));
+    }
+    
+    // Every change made from now on, for redrawing, logging and the like
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<Event> {
+        self.observers.subscribe()
+    }
+    
+    pub fn seed(&self) -> u64 { self.seed }
+    pub fn difficulty(&self) -> Difficulty { self.difficulty }
     pub fn chunks_won(&self) -> u64 { self.chunks_won }
     pub fn chunks_lost(&self) -> u64 { self.chunks_lost }
-    pub fn get_chunk(&self, chunk: Coord<isize>) -> Option<&Chunk> { self.chunks.get(&chunk) }
     
-    fn allocate_with_surround(&mut self, chunk: Coord<isize>, square: Coord<usize>) {
-        use hashbrown::hash_map::Entry::Vacant;
+    pub fn score(&self) -> i64 {
+        points(self.chunks_won, self.chunks_lost)
+    }
+    
+    // Chunks finished from now on are put down to `player`
+    pub fn act_as(&mut self, player: Option<PlayerId>) {
+        self.player = player;
+    }
+    
+    // Who finished the chunk, if it was finished while someone was acting
+    pub fn owner(&self, chunk: Coord<isize>) -> Option<PlayerId> {
+        self.owners.get(&chunk).cloned()
+    }
+    
+    pub fn solve_ratio(&self) -> Option<f64> {
+        let finished = self.chunks_won + self.chunks_lost;
+        if finished == 0 { None } else { Some(self.chunks_won as f64 / finished as f64) }
+    }
+    
+    // Shows a chunk as it is in a game played elsewhere, replacing whatever
+    // was there. Mirrored chunks aren't played locally.
+    pub fn mirror_chunk(&mut self, coord: Coord<isize>, views: &[SquareView], outcome: Option<Outcome>) {
+        self.frozen.remove(&coord);
+        self.chunks.insert(coord, Chunk::mirror(views, outcome));
+    }
+    
+    pub fn mirror_counts(&mut self, won: u64, lost: u64) {
+        self.chunks_won = won;
+        self.chunks_lost = lost;
+    }
+    
+    // Only sees chunks in memory; `page_in` brings paged-out ones back
+    pub fn get_chunk(&self, chunk: Coord<isize>) -> Option<ChunkRef<'_>> {
+        match self.chunks.get(&chunk) {
+            Some(live) => Some(ChunkRef::Live(live)),
+            None => self.frozen.get(&chunk).map(ChunkRef::Frozen),
+        }
+    }
+    
+    // Chunks that can't be read back stay in the store, and moves that
+    // need them aren't made, so nothing is generated over them
+    pub fn take_store_error(&mut self) -> Option<io::Error> {
+        self.store_error.take()
+    }
+    
+    // Counts paged-out chunks too
+    pub fn allocated(&self) -> usize {
+        self.resident() + self.store.as_ref().map_or(0, |store| store.len())
+    }
+    
+    fn resident(&self) -> usize {
+        self.chunks.len() + self.frozen.len()
+    }
+    
+    fn mines(&self, chunk: Coord<isize>) -> Option<BitField> {
+        self.get_chunk(chunk).map(|chunk| match chunk {
+            ChunkRef::Live(live) => live.mines,
+            ChunkRef::Frozen(frozen) => frozen.mines,
+        })
+    }
+    pub fn last_explosion(&self) -> Option<Coord<isize>> { self.last_explosion }
+    
+    pub fn bookmark(&self, name: &str) -> Option<Coord<isize>> {
+        self.bookmarks.get(name).cloned()
+    }
+    
+    pub fn set_bookmark(&mut self, name: String, world_coord: Coord<isize>) {
+        self.bookmarks.insert(name, world_coord);
+    }
+    
+    pub fn remove_bookmark(&mut self, name: &str) -> Option<Coord<isize>> {
+        self.bookmarks.remove(name)
+    }
+    
+    pub fn nearest_unfinished(&self, world_coord: Coord<isize>) -> Option<Coord<isize>> {
+        const HALF_CHUNK: Coord<isize> = Coord::squared(chunk::DIMENSION as isize / 2);
 
-        if let Vacant(entry) = self.chunks.entry(chunk) {
-            entry.insert(loop {
-                // Ensure first click is not a mine
-                let insert = Chunk::with_mines();
-                if insert.mines.get(square) { continue; }
-                break insert;
-            });
+        let paged_out = self.store.iter().flat_map(|store| store.unfinished());
+
+        self.chunks
+            .iter()
+            .filter(|(_, chunk)| ChunkRef::Live(chunk).unfinished())
+            .map(|(&coord, _)| coord)
+            .chain(paged_out)
+            .map(|coord| Coord::from(AbsoluteCoord { chunk: coord, square: Coord::default() }) + HALF_CHUNK)
+            .min_by_key(|&center| {
+                let Coord(x, y) = center - world_coord;
+                x * x + y * y
+            })
+    }
+    
+    // Loads the chunks in `min..max` that were paged out, then pages out
+    // others until the cache fits, starting with finished and far ones
+    pub fn page_in(&mut self, min: Coord<isize>, max: Coord<isize>) {
+        if self.store.is_none() { return; }
+        
+        for coord in IndexIterSigned::new(max - min, min) {
+            if let Err(error) = self.load(coord) {
+                self.store_error = Some(error);
+            }
         }
         
-        IndexIterSigned::self_and_adjacent(chunk)
-            .filter(|&coord| coord != chunk)
-            .for_each(|coord|
-                if let Vacant(entry) = self.chunks.entry(coord) {
-                    entry.insert(Chunk::with_mines());
-                }
-            );
+        if self.resident() <= self.cache_limit { return; }
+        
+        let center = (min + max) / Coord::squared(2);
+        let in_view = |Coord(x, y): Coord<isize>| min.0 <= x && x < max.0 && min.1 <= y && y < max.1;
+        
+        let mut candidates: Vec<_> = self.chunks
+            .keys()
+            .map(|&coord| (true, coord))
+            .chain(self.frozen.keys().map(|&coord| (false, coord)))
+            .filter(|&(_, coord)| !in_view(coord))
+            .map(|(unfinished, coord)| {
+                let Coord(x, y) = coord - center;
+                (unfinished, -(x * x + y * y), coord)
+            })
+            .collect();
+        candidates.sort_by_key(|&(unfinished, nearness, _)| (unfinished, nearness));
+        
+        let excess = self.resident() - self.cache_limit;
+        
+        for &(_, _, coord) in candidates.iter().take(excess) {
+            let chunk = match self.chunks.get(&coord) {
+                Some(live) => ChunkRef::Live(live),
+                None => ChunkRef::Frozen(&self.frozen[&coord]),
+            };
+            let saved = self.store.as_mut().unwrap().save(coord, chunk);
+            
+            // A chunk that can't be written out just stays in memory
+            if saved.is_ok() {
+                self.chunks.remove(&coord);
+                self.frozen.remove(&coord);
+            }
+        }
+    }
+    
+    // Asks the workers for the chunks in `min..max` not generated yet, and
+    // takes whatever they've finished. Never waits for them.
+    pub fn pregenerate(&mut self, min: Coord<isize>, max: Coord<isize>) {
+        let generator = match self.generator.as_mut() {
+            Some(generator) => generator,
+            None => return,
+        };
+        
+        self.pregenerated.extend(generator.finished());
+        
+        let in_range = |Coord(x, y): Coord<isize>| min.0 <= x && x < max.0 && min.1 <= y && y < max.1;
+        self.pregenerated.retain(|&coord, _| in_range(coord));
+        
+        let (chunks, frozen, pregenerated, store) = (&self.chunks, &self.frozen, &self.pregenerated, &self.store);
+        let missing = IndexIterSigned::new(max - min, min).filter(|&coord| {
+            !(chunks.contains_key(&coord)
+                || frozen.contains_key(&coord)
+                || pregenerated.contains_key(&coord)
+                || store.as_ref().is_some_and(|store| store.contains(coord)))
+        });
+        generator.request(missing);
+    }
+    
+    pub fn generating(&self) -> bool {
+        self.generator.as_ref().is_some_and(Generator::busy)
     }
     
-    pub fn touch(&mut self, world_coords: &[Coord<isize>]) -> Option<Vec<Coord<isize>>> {
-        let mut to_click = Vec::with_capacity(64);
+    // Whether the chunk is in memory, after reloading it if it was paged out
+    fn load(&mut self, coord: Coord<isize>) -> io::Result<bool> {
+        if self.chunks.contains_key(&coord) || self.frozen.contains_key(&coord) { return Ok(true); }
+        
+        let store = match self.store.as_mut() {
+            Some(store) => store,
+            None => return Ok(false),
+        };
+        
+        match store.take(coord)? {
+            Some(Slot::Live(chunk)) => { self.chunks.insert(coord, chunk); },
+            Some(Slot::Frozen(chunk)) => { self.frozen.insert(coord, chunk); },
+            None => return Ok(false),
+        }
+        Ok(true)
+    }
+    
+    // Moves a finished chunk into its compact form. The move that finished
+    // it brought its surround into memory.
+    fn freeze(&mut self, coord: Coord<isize>) {
+        let halo = Halo::gather(|offset| self.mines(coord + offset).unwrap());
+        let chunk = self.chunks.remove(&coord).unwrap();
+        self.frozen.insert(coord, FrozenChunk::freeze(&chunk, halo));
+    }
+    
+    // A new chunk, taken from the workers if they got to it first. Mines
+    // are redrawn until none lands on `clear`.
+    fn fresh_chunk(&mut self, coord: Coord<isize>, clear: BitField) -> Chunk {
+        if let Some(generated) = self.pregenerated.remove(&coord) {
+            let mines = generated.mines();
+            
+            if (mines & clear).is_empty() {
+                if !clear.is_empty() { self.counted = Some((coord, generated)); }
+                return Chunk::restore(Stage::Enmined, mines, BitField::default(), BitField::default(), Default::default());
+            }
+        }
+        
+        let mut rng = chunk_rng(self.seed, coord);
+        loop {
+            let chunk = Chunk::with_mines(&mut rng, self.difficulty);
+            if !(chunk.mines & clear).is_empty() { continue; }
+            return chunk;
+        }
+    }
+    
+    fn allocate(&mut self, coord: Coord<isize>, clear: BitField) {
+        let fresh = self.fresh_chunk(coord, clear);
+        self.chunks.insert(coord, fresh);
+        self.observers.emit(Event::ChunkAllocated(coord));
+    }
+    
+    // Nothing is allocated unless the whole surround could be read back
+    fn allocate_with_surround(&mut self, chunk: Coord<isize>, square: Coord<usize>) -> io::Result<()> {
+        let mut missing = Vec::with_capacity(9);
+        for coord in IndexIterSigned::self_and_adjacent(chunk) {
+            if !self.load(coord)? { missing.push(coord); }
+        }
+        
+        for coord in missing {
+            // Ensure first click is not a mine
+            let mut clear = BitField::default();
+            if coord == chunk { clear.set(square); }
+            self.allocate(coord, clear);
+        }
+        Ok(())
+    }
+    
+    // Keeps mines out of the 3x3 around the game's first click, so it opens
+    // up an area rather than a single number. Chunks already generated,
+    // say by placing a flag first, are drawn again the way they would have
+    // been for this click, keeping their flags.
+    fn clear_opening(&mut self, world_coord: Coord<isize>) {
+        let center = AbsoluteCoord::from(world_coord).chunk;
+        let mut masks: Vec<(Coord<isize>, BitField)> = Vec::with_capacity(4);
+        
+        for around in IndexIterSigned::self_and_adjacent(world_coord) {
+            let AbsoluteCoord { chunk, square } = around.into();
+            match masks.iter_mut().find(|(coord, _)| *coord == chunk) {
+                Some((_, mask)) => mask.set(square),
+                None => {
+                    let mut mask = BitField::default();
+                    mask.set(square);
+                    masks.push((chunk, mask));
+                },
+            }
+        }
+        
+        // The clicked chunk goes last so the worker's counts kept for it
+        // aren't replaced by a neighbour's
+        masks.sort_by_key(|&(coord, _)| coord == center);
+        
+        for (coord, mask) in masks {
+            match self.load(coord) {
+                Ok(false) => self.allocate(coord, mask),
+                Ok(true) => if self.untouched(coord) {
+                    let flags = self.chunks[&coord].flags;
+                    let mut fresh = self.fresh_chunk(coord, mask);
+                    fresh.flags = flags;
+                    self.chunks.insert(coord, fresh);
+                },
+                Err(error) => self.store_error = Some(error),
+            }
+        }
+    }
+    
+    // Whether neither the chunk nor any counted around it depends on its
+    // mines yet, as in a reopened world. Chunks that can't be read back
+    // might, so they count as touched.
+    fn untouched(&mut self, coord: Coord<isize>) -> bool {
+        IndexIterSigned::self_and_adjacent(coord).all(|around| match self.load(around) {
+            Ok(false) => true,
+            Ok(true) => self.get_chunk(around).unwrap().stage() != Stage::Neighbored,
+            Err(error) => {
+                self.store_error = Some(error);
+                false
+            },
+        })
+    }
+    
+    // Clicks the given squares, starting a cascade from any blank ones.
+    // Returns whether `spread` has more of the cascade to reveal.
+    pub fn touch(&mut self, world_coords: &[Coord<isize>]) -> bool {
+        self.wave.clear();
         
         for &world_coord in world_coords {
-            let AbsoluteCoord { chunk, square } = world_coord.into();
-
-            self.allocate_with_surround(chunk, square);
-            self.calc_neighbors(chunk);
+            if !self.reveal(world_coord) {
+                self.wave.clear();
+                return false;
+            }
+        }
+        
+        !self.wave.is_empty()
+    }
+    
+    // Clicks around the blank squares of the last wave, one wave per call so
+    // the cascade can be drawn as it goes
+    pub fn spread(&mut self) -> bool {
+        mem::swap(&mut self.wave, &mut self.fringe);
+        self.wave.clear();
+        
+        for index in 0..self.fringe.len() {
+            let center = self.fringe[index];
+            let adjacent = IndexIterSigned::self_and_adjacent(center).filter(|&coord| coord != center);
             
-            {
-                let touched_chunk = self.chunks.get_mut(&chunk).unwrap();
-                
-                if touched_chunk.clicked.get(square) { continue; }
-                
-                // Actually click
-                touched_chunk.flags.unset(square);
-                touched_chunk.clicked.set(square);
-                
-                if touched_chunk.mines.get(square) {
-                    touched_chunk.status = chunk::Status::Lost;
-                    self.chunks_lost += 1;
-                    return None;
-                }
-                
-                if touched_chunk.is_won() {
-                    touched_chunk.status = chunk::Status::Won;
-                    self.chunks_won += 1;
+            for world_coord in adjacent {
+                if !self.reveal(world_coord) {
+                    self.wave.clear();
+                    return false;
                 }
             }
+        }
+        
+        !self.wave.is_empty()
+    }
+    
+    // Clicks a square, queueing it for the next wave if it's blank. Squares
+    // already clicked are skipped, so each one is only visited once.
+    // Returns false if it was a mine.
+    fn reveal(&mut self, world_coord: Coord<isize>) -> bool {
+        let AbsoluteCoord { chunk, square } = world_coord.into();
+        
+        if !self.opened {
+            self.opened = true;
+            self.clear_opening(world_coord);
+        }
+        
+        let ready = match self.get_chunk(chunk) {
+            Some(ChunkRef::Live(live)) => live.stage() == Stage::Neighbored,
+            // Finished chunks don't take input
+            Some(ChunkRef::Frozen(_)) => return true,
+            None => false,
+        };
+        
+        // Once paged, the surround may have gone since it was counted
+        if !ready || self.store.is_some() {
+            if let Err(error) = self.allocate_with_surround(chunk, square) {
+                self.store_error = Some(error);
+                return true;
+            }
+            self.calc_neighbors(chunk);
+            
+            if self.frozen.contains_key(&chunk) { return true; }
+        }
+        
+        let (mined, won, num_neighbors) = {
+            let touched_chunk = self.chunks.get_mut(&chunk).unwrap();
             
-            let num_neighbors = self
-                .chunks
-                .get(&chunk)
-                .unwrap()
-                .neighbors
-                .get(square);
+            if touched_chunk.clicked.get(square) { return true; }
             
-            if num_neighbors == 0 {
-                let fringe = IndexIterSigned::self_and_adjacent(Coord::<isize>::default())
-                    .filter(|&offset| offset != Coord::default())
-                    .map(|offset| offset + world_coord);
-                
-                to_click.extend(fringe);
+            // Actually click
+            touched_chunk.flags.unset(square);
+            touched_chunk.clicked.set(square);
+            
+            let mined = touched_chunk.mines.get(square);
+            (mined, !mined && touched_chunk.is_won(), touched_chunk.neighbors.get(square))
+        };
+        self.observers.emit(Event::SquareRevealed(world_coord));
+        
+        if mined {
+            self.last_explosion = Some(world_coord);
+            self.finish(chunk, Outcome::Lost);
+            return false;
+        }
+        
+        if won {
+            self.finish(chunk, Outcome::Won);
+        }
+        
+        if num_neighbors == 0 {
+            self.wave.push(world_coord);
+        }
+        true
+    }
+    
+    // Clicks the unflagged squares around a revealed number once as many
+    // mines around it are flagged or showing. Returns like `touch`.
+    pub fn chord(&mut self, world_coord: Coord<isize>) -> bool {
+        let count = match self.square_view(world_coord) {
+            Some(SquareView::Clicked(count)) => count as usize,
+            _ => return false,
+        };
+        
+        let mut around = Vec::with_capacity(8);
+        let mut marked = 0;
+        for neighbor in IndexIterSigned::self_and_adjacent(world_coord).filter(|&coord| coord != world_coord) {
+            match self.square_view(neighbor) {
+                Some(SquareView::Unclicked) => around.push(neighbor),
+                Some(SquareView::Flagged) | Some(SquareView::Penalty) | Some(SquareView::Points) => marked += 1,
+                _ => (),
             }
         }
         
-        to_click.optionalize()
+        if marked != count || around.is_empty() { return false; }
+        self.touch(&around)
+    }
+    
+    fn square_view(&self, world_coord: Coord<isize>) -> Option<SquareView> {
+        let AbsoluteCoord { chunk, square } = world_coord.into();
+        self.get_chunk(chunk).map(|chunk| chunk.view(square))
     }
     
     pub fn toggle_flag(&mut self, world_coord: Coord<isize>) {
         let AbsoluteCoord { chunk, square } = world_coord.into();
 
-        self.allocate_with_surround(chunk, square);
-        let chunk = self.chunks.get_mut(&chunk).unwrap();
+        if let Err(error) = self.allocate_with_surround(chunk, square) {
+            self.store_error = Some(error);
+            return;
+        }
+        let coord = chunk;
+        let chunk = match self.chunks.get_mut(&coord) {
+            Some(chunk) => chunk,
+            None => return, // Finished chunks don't take input
+        };
         
         if !chunk.clicked.get(square) {
             chunk.flags.toggle(square);
+            self.observers.emit(Event::FlagToggled(world_coord));
         }
         
         if chunk.is_won() {
-            chunk.status = chunk::Status::Won;
-            self.chunks_won += 1;
+            self.finish(coord, Outcome::Won);
         }
     }
     
-    fn calc_neighbors(&mut self, coord: Coord<isize>) {
+    // Every chunk is won or lost here and nowhere else. Since finished
+    // chunks are frozen and ignore input, that happens once per chunk.
+    fn finish(&mut self, coord: Coord<isize>, outcome: Outcome) {
+        self.chunks.get_mut(&coord).unwrap().finish(outcome);
+        if let Some(player) = self.player {
+            self.owners.insert(coord, player);
+        }
+        
+        let event = match outcome {
+            Outcome::Won  => { self.chunks_won  += 1; Event::ChunkWon(coord) },
+            Outcome::Lost => { self.chunks_lost += 1; Event::ChunkLost(coord) },
+        };
+        
+        self.freeze(coord);
+        self.observers.emit(event);
+        self.observers.emit(Event::ScoreChanged(self.score()));
+    }
+    
+    pub fn calc_neighbors(&mut self, coord: Coord<isize>) {
         debug_assert!(
             IndexIterSigned::self_and_adjacent(coord)
-                .all(|chunk| self.chunks.contains_key(&chunk))
+                .all(|chunk| self.get_chunk(chunk).is_some())
         );
 
-        let neighbors = {
-            let mut canvas = NybbleField::default();
-            let center = self.chunks.get(&coord).unwrap();
-            
-            if center.status != chunk::Status::Enmined { return; }
-            
-            let surround = IndexIterSigned::self_and_adjacent(coord)
-                    .map(|target| self.chunks.get(&target))
-                    .collect::<Option<Vec<_>>>()
-                    .unwrap();
-            
-            for square_index in chunk::all_squares() {
-                if center.mines.get(square_index) { continue; } // Mine squares have no count
-
-                let square_index_i = Coord::<isize>::from(square_index);
-                
-                let count = IndexIterSigned::self_and_adjacent(Coord::<isize>::default())
-                    .map(|offset| {
-                        const DIMENSION_COORD: Coord<isize> = Coord::squared(chunk::DIMENSION as isize);
-
-                        let RENAME_ME = square_index_i
-                            .add(offset)
-                            .add(DIMENSION_COORD)
-                            .div(DIMENSION_COORD);
-                        
-                        let chunk = RENAME_ME
-                            .mul(Coord(3, 1))
-                            .sum() as usize;
-
-                        let square: Coord<usize> = square_index_i
-                            .add(offset)
-                            .add(
-                                Coord::squared(2)
-                                    .sub(RENAME_ME)
-                                    .mul(DIMENSION_COORD)
-                            )
-                            .rem(DIMENSION_COORD)
-                            .into();
-                        
-                        (chunk, square)
-                    })
-                    .filter(|&(chunk, square)| surround[chunk].mines.get(square))
-                    .count();
-                
-                debug_assert!(count < std::u8::MAX.into());
-                
-                canvas.set(square_index, count as u8);
-            }
-            
-            canvas
+        // Counted lazily, the first time the chunk is clicked
+        match self.chunks.get(&coord) {
+            Some(center) if center.stage() == Stage::Enmined => (),
+            _ => return, // Already counted, or frozen and finished
+        }
+        
+        let mut surround = [BitField::default(); 9];
+        for (mines, target) in surround.iter_mut().zip(IndexIterSigned::self_and_adjacent(coord)) {
+            *mines = self.mines(target).unwrap();
+        }
+        
+        let neighbors = match self.counted.take() {
+            // Counted by a worker from the same mines
+            Some((counted, generated)) if counted == coord && generated.surround == surround => generated.neighbors,
+            _ => neighbors::count(&neighbors::pad(&surround)),
         };
 
-        let dest = self.chunks.get_mut(&coord).unwrap();
-        dest.neighbors = neighbors;
-        dest.status = chunk::Status::Neighbored;
+        self.chunks.get_mut(&coord).unwrap().set_neighbors(neighbors);
     }
 }
 
+// Each chunk draws from its own stream so generation order doesn't matter
+fn chunk_rng(seed: u64, Coord(x, y): Coord<isize>) -> XorShiftRng {
+    let seed = splitmix(seed ^ splitmix(x as u64) ^ splitmix(y as u64).rotate_left(32));
+    let words = [seed as u32, (seed >> 32) as u32, splitmix(seed) as u32, 1];
+    XorShiftRng::from_seed(words)
+}
+
+fn splitmix(value: u64) -> u64 {
+    let value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,10 +716,445 @@ mod tests {
         let active_count = game
             .chunks
             .values()
-            .filter(|&chunk| chunk.status != chunk::Status::Enmined)
+            .filter(|&chunk| chunk.stage() != Stage::Enmined)
             .count();
         
         assert_eq!(game.chunks.len(), 25);
         assert_eq!(active_count, 5);
     }
+    
+    #[test]
+    fn cascade_waves() {
+        let mut game = Game::with_settings(7, Difficulty::Easy);
+        let mut waves = 0;
+        let mut spreading = game.touch(&[Coord(0, 0)]);
+        
+        while spreading {
+            // Each blank square is only queued once
+            let mut wave = game.wave.clone();
+            wave.sort_by_key(|&Coord(x, y)| (x, y));
+            wave.dedup();
+            assert_eq!(wave.len(), game.wave.len());
+            
+            waves += 1;
+            spreading = game.spread();
+        }
+        assert!(waves > 1);
+        
+        // Everything around a revealed blank square is revealed too
+        let chunks: Vec<_> = game.chunks.keys().chain(game.frozen.keys()).cloned().collect();
+        for coord in chunks {
+            let chunk = game.get_chunk(coord).unwrap();
+            for square in chunk::all_squares() {
+                if let SquareView::Clicked(0) = chunk.view(square) {
+                    let center = Coord::from(AbsoluteCoord { chunk: coord, square });
+                    for around in IndexIterSigned::self_and_adjacent(center) {
+                        let AbsoluteCoord { chunk, square } = around.into();
+                        match game.get_chunk(chunk).unwrap().view(square) {
+                            SquareView::Clicked(_) => (),
+                            view => panic!("{:?} next to blank {:?} is {:?}", around, center, view),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn opening_is_clear() {
+        // Corners and edges, so the opening spans several chunks
+        let clicks = [Coord(0, 0), Coord(7, 7), Coord(8, -1), Coord(-9, 4), Coord(3, 3)];
+        
+        for seed in 0..20 {
+            for &click in clicks.iter() {
+                let mut game = Game::with_settings(seed, Difficulty::Hard);
+                game.touch(&[click]);
+                
+                for around in IndexIterSigned::self_and_adjacent(click) {
+                    let AbsoluteCoord { chunk, square } = around.into();
+                    assert!(!game.mines(chunk).unwrap().get(square), "mine at {:?}, seed {}", around, seed);
+                }
+                
+                let AbsoluteCoord { chunk, square } = click.into();
+                match game.get_chunk(chunk).unwrap().view(square) {
+                    SquareView::Clicked(0) => (),
+                    view => panic!("first click at {:?} shows {:?}, seed {}", click, view, seed),
+                }
+                
+                // Same seed, same click, same world
+                let mut again = Game::with_settings(seed, Difficulty::Hard);
+                again.touch(&[click]);
+                for (coord, chunk) in &game.chunks {
+                    assert!(chunk.mines == again.chunks[coord].mines);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn flags_before_opening() {
+        let clicks = [Coord(0, 0), Coord(7, 7), Coord(8, -1), Coord(-9, 4), Coord(3, 3)];
+        
+        for seed in 0..20 {
+            for &click in clicks.iter() {
+                let mut game = Game::with_settings(seed, Difficulty::Hard);
+                game.toggle_flag(click + Coord(1, 1));
+                game.toggle_flag(click + Coord(-20, 3));
+                game.touch(&[click]);
+                
+                let AbsoluteCoord { chunk, square } = click.into();
+                match game.get_chunk(chunk).unwrap().view(square) {
+                    SquareView::Clicked(0) => (),
+                    view => panic!("first click at {:?} shows {:?}, seed {}", click, view, seed),
+                }
+                assert_eq!(game.square_view(click + Coord(-20, 3)), Some(SquareView::Flagged));
+                
+                // The same world as without the flags
+                let mut unflagged = Game::with_settings(seed, Difficulty::Hard);
+                unflagged.touch(&[click]);
+                for (coord, chunk) in &unflagged.chunks {
+                    assert!(chunk.mines == game.chunks[coord].mines, "{:?}, seed {}", coord, seed);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn seeded_generation() {
+        // The opening depends on where the game starts, the rest doesn't
+        let order_a = [Coord(-40, 20), Coord(0, 0), Coord(40, -40)];
+        let order_b = [Coord(-40, 20), Coord(40, -40), Coord(0, 0)];
+        
+        let mut game_a = Game::with_settings(1234, Difficulty::Normal);
+        let mut game_b = Game::with_settings(1234, Difficulty::Normal);
+        for &coord in &order_a { game_a.touch(&[coord]); }
+        for &coord in &order_b { game_b.touch(&[coord]); }
+        
+        assert_eq!(game_a.chunks.len(), game_b.chunks.len());
+        for (coord, chunk) in &game_a.chunks {
+            assert!(chunk.mines == game_b.chunks[coord].mines);
+        }
+    }
+    
+    #[test]
+    fn pregenerated_world() {
+        let (min, max) = (Coord(-6, -6), Coord(6, 6));
+        let mut plain = Game::with_settings(31, Difficulty::Easy);
+        let mut threaded = Game::with_settings(31, Difficulty::Easy);
+        threaded.set_generator(3);
+        
+        threaded.pregenerate(min, max);
+        while threaded.generating() {
+            std::thread::yield_now();
+            threaded.pregenerate(min, max);
+        }
+        assert_eq!(threaded.pregenerated.len(), 144);
+        
+        // First clicks stay safe even where the workers put a mine
+        let mined = threaded.pregenerated[&Coord(3, -2)].mines();
+        let square = chunk::all_squares().find(|&square| mined.get(square)).unwrap();
+        let mut clicks = vec![Coord::from(AbsoluteCoord { chunk: Coord(3, -2), square })];
+        clicks.extend((-40..40).step_by(11).map(|x| Coord(x, x / 2)));
+        
+        for game in [&mut plain, &mut threaded].iter_mut() {
+            for &click in &clicks {
+                game.touch(&[click]);
+                while game.spread() {}
+            }
+        }
+        
+        assert_eq!(plain.chunks_lost(), threaded.chunks_lost());
+        assert_eq!(plain.allocated(), threaded.allocated());
+        for &coord in plain.chunks.keys().chain(plain.frozen.keys()) {
+            let expected = region::encode(plain.get_chunk(coord).unwrap());
+            assert_eq!(region::encode(threaded.get_chunk(coord).unwrap())[..], expected[..], "{:?}", coord);
+        }
+    }
+    
+    #[test]
+    fn paging() {
+        const CACHE: usize = 12;
+
+        let mut game = Game::with_settings(99, Difficulty::Normal);
+        game.set_paging(RegionStore::temporary().unwrap(), CACHE).unwrap();
+        
+        for x in (0..240).step_by(40) {
+            game.touch(&[Coord(x, 3)]);
+            while game.spread() {}
+        }
+        
+        let before: Vec<_> = game.chunks
+            .keys()
+            .chain(game.frozen.keys())
+            .map(|&coord| (coord, region::encode(game.get_chunk(coord).unwrap()).to_vec()))
+            .collect();
+        let unfinished = game.nearest_unfinished(Coord(100, 100));
+        assert!(before.len() > CACHE);
+        
+        game.page_in(Coord(0, 0), Coord(1, 1));
+        let store = game.store.as_ref().unwrap();
+        assert_eq!(game.resident(), CACHE);
+        assert_eq!(game.resident() + store.len(), before.len());
+        assert!(game.get_chunk(Coord(0, 0)).is_some());
+        assert_eq!(game.nearest_unfinished(Coord(100, 100)), unfinished);
+        
+        for (coord, bytes) in before {
+            game.page_in(coord, coord + Coord(1, 1));
+            assert_eq!(region::encode(game.get_chunk(coord).unwrap()).to_vec(), bytes);
+            assert!(game.resident() <= CACHE);
+        }
+    }
+    
+    #[test]
+    fn freezing_paged_out_surround() {
+        let mut plain = Game::with_settings(0, Difficulty::Normal);
+        let mut paged = Game::with_settings(0, Difficulty::Normal);
+        paged.set_paging(RegionStore::temporary().unwrap(), 1).unwrap();
+        
+        for game in [&mut plain, &mut paged].iter_mut() {
+            game.touch(&[Coord(4, 4)]);
+            while game.spread() {}
+        }
+        
+        // Only the chunk about to be lost stays in memory
+        paged.page_in(Coord(0, 0), Coord(1, 1));
+        assert_eq!(paged.resident(), 1);
+        
+        let origin = &plain.chunks[&Coord(0, 0)];
+        let mine = Coord::<isize>::from(chunk::all_squares().find(|&square| origin.mines.get(square)).unwrap());
+        for game in [&mut plain, &mut paged].iter_mut() {
+            game.touch(&[mine]);
+            assert_eq!(game.chunks_lost(), 1);
+        }
+        
+        let (expected, frozen) = (plain.get_chunk(Coord(0, 0)).unwrap(), paged.get_chunk(Coord(0, 0)).unwrap());
+        for square in chunk::all_squares() {
+            assert_eq!(frozen.view(square), expected.view(square), "{:?}", square);
+        }
+    }
+    
+    #[test]
+    fn saved_worlds() {
+        let scratch = RegionStore::temporary().unwrap();
+        let dir = scratch.dir().join("world");
+        
+        let mut game = Game::with_settings(4, Difficulty::Normal);
+        game.set_paging(RegionStore::open(&dir).unwrap(), 100).unwrap();
+        game.touch(&[Coord(4, 4)]);
+        while game.spread() {}
+        let origin = &game.chunks[&Coord(0, 0)];
+        let mine = Coord::<isize>::from(chunk::all_squares().find(|&square| origin.mines.get(square)).unwrap());
+        game.touch(&[mine]);
+        game.set_bookmark("first mine".to_owned(), mine);
+        
+        let before: Vec<_> = game.chunks
+            .keys()
+            .chain(game.frozen.keys())
+            .map(|&coord| (coord, region::encode(game.get_chunk(coord).unwrap()).to_vec()))
+            .collect();
+        let allocated = game.allocated();
+        game.save().unwrap();
+        assert_eq!(game.resident(), 0);
+        
+        let mut reopened = Game::with_settings(4, Difficulty::Normal);
+        reopened.set_paging(RegionStore::open(&dir).unwrap(), 100).unwrap();
+        assert_eq!((reopened.chunks_won(), reopened.chunks_lost()), (0, 1));
+        assert_eq!(reopened.allocated(), allocated);
+        assert!(reopened.opened);
+        assert_eq!(reopened.bookmark("first mine"), Some(mine));
+        for (coord, bytes) in before {
+            reopened.page_in(coord, coord + Coord(1, 1));
+            assert_eq!(region::encode(reopened.get_chunk(coord).unwrap()).to_vec(), bytes);
+        }
+        
+        // Another world's chunks don't fit this one
+        let mut other = Game::with_settings(5, Difficulty::Normal);
+        assert!(other.set_paging(RegionStore::open(&dir).unwrap(), 100).is_err());
+    }
+    
+    #[test]
+    fn unreadable_chunks() {
+        let mut game = Game::with_settings(5, Difficulty::Normal);
+        game.set_paging(RegionStore::temporary().unwrap(), 1).unwrap();
+        game.touch(&[Coord(4, 4)]);
+        while game.spread() {}
+        game.page_in(Coord(10, 10), Coord(11, 11));
+        
+        // Cut off every slot of the region around the origin
+        let path = game.store.as_ref().unwrap().dir().join("r.0.0.bin");
+        std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(8).unwrap();
+        std::fs::OpenOptions::new().write(true).open(path.with_file_name("r.-1.-1.bin")).unwrap().set_len(8).unwrap();
+        
+        let allocated = game.allocated();
+        game.page_in(Coord(-1, -1), Coord(2, 2));
+        assert!(game.take_store_error().is_some());
+        assert_eq!(game.allocated(), allocated);
+        
+        // Nor are they played over
+        game.toggle_flag(Coord(-4, -4));
+        assert!(game.take_store_error().is_some());
+        assert!(game.get_chunk(Coord(-1, -1)).is_none());
+        assert_eq!(game.allocated(), allocated);
+    }
+    
+    #[test]
+    fn nearest_unfinished() {
+        let mut game = Game::default();
+        assert_eq!(game.nearest_unfinished(Coord(0, 0)), None);
+        
+        game.touch(&[Coord(0, 0)]);
+        game.touch(&[Coord(-20, 40)]);
+        
+        assert_eq!(game.nearest_unfinished(Coord(  0,  0)), Some(Coord(  4,  4)));
+        assert_eq!(game.nearest_unfinished(Coord(-16, 30)), Some(Coord(-20, 44)));
+    }
+    
+    #[test]
+    fn mirrored_views() {
+        let mut game = Game::with_settings(9, Difficulty::Hard);
+        for &(x, y) in [(0, 0), (13, -2), (-6, 21), (30, 30), (-17, -9)].iter() {
+            game.touch(&[Coord(x, y)]);
+            while game.spread() {}
+        }
+        game.toggle_flag(Coord(1, 9));
+        
+        let mut mirror = Game::default();
+        let coords: Vec<_> = game.chunks.keys().chain(game.frozen.keys()).cloned().collect();
+        
+        for &coord in coords.iter() {
+            let chunk = game.get_chunk(coord).unwrap();
+            let views: Vec<_> = chunk::all_squares().map(|square| chunk.view(square)).collect();
+            mirror.mirror_chunk(coord, &views, chunk.outcome());
+        }
+        
+        for &coord in coords.iter() {
+            let (played, mirrored) = (game.get_chunk(coord).unwrap(), mirror.get_chunk(coord).unwrap());
+            assert_eq!(played.outcome(), mirrored.outcome());
+            for square in chunk::all_squares() {
+                assert_eq!(played.view(square), mirrored.view(square), "{:?} {:?}", coord, square);
+            }
+        }
+    }
+    
+    #[test]
+    fn chord() {
+        let mut game = Game::with_settings(3, Difficulty::Normal);
+        game.touch(&[Coord(4, 4)]);
+        while game.spread() {}
+        
+        let is_mine = |game: &Game, coord: Coord<isize>| {
+            let AbsoluteCoord { chunk, square } = coord.into();
+            game.mines(chunk).unwrap().get(square)
+        };
+        let around = |coord: Coord<isize>| IndexIterSigned::self_and_adjacent(coord).filter(move |&other| other != coord);
+        
+        // A number with both mines and safe squares still to click around it
+        let number = IndexIterSigned::new(Coord::squared(8), Coord::default())
+            .find(|&coord| {
+                let unclicked: Vec<_> = around(coord)
+                    .filter(|&other| game.square_view(other) == Some(SquareView::Unclicked))
+                    .collect();
+                game.square_view(coord).is_some_and(|view| view != SquareView::Clicked(0) && view != SquareView::Unclicked)
+                    && unclicked.iter().any(|&other| is_mine(&game, other))
+                    && unclicked.iter().any(|&other| !is_mine(&game, other))
+            })
+            .unwrap();
+        
+        // Nothing happens until enough flags are down
+        assert!(!game.chord(number));
+        assert!(around(number).any(|other| game.square_view(other) == Some(SquareView::Unclicked)));
+        
+        for mine in around(number).filter(|&other| is_mine(&game, other)).collect::<Vec<_>>() {
+            game.toggle_flag(mine);
+        }
+        game.chord(number);
+        while game.spread() {}
+        
+        for other in around(number) {
+            let view = game.square_view(other).unwrap();
+            if is_mine(&game, other) {
+                assert!(view == SquareView::Flagged || view == SquareView::Points, "{:?}", other);
+            } else {
+                assert!(view != SquareView::Unclicked, "{:?}", other);
+            }
+        }
+        assert_eq!(game.chunks_lost(), 0);
+    }
+    
+    #[test]
+    fn owners() {
+        let mut game = Game::with_settings(3, Difficulty::Normal);
+        game.touch(&[Coord(4, 4)]);
+        while game.spread() {}
+        
+        // Nobody was acting, so nobody owns anything
+        let origin = &game.chunks[&Coord(0, 0)];
+        let squares: Vec<_> = chunk::all_squares().filter(|&square| !origin.clicked.get(square)).collect();
+        let (mines, safe): (Vec<_>, Vec<_>) = squares.into_iter().partition(|&square| origin.mines.get(square));
+        
+        game.act_as(Some(2));
+        for &square in safe.iter() {
+            game.touch(&[square.into()]);
+            while game.spread() {}
+        }
+        for &square in mines.iter() {
+            game.toggle_flag(square.into());
+        }
+        assert_eq!(game.owner(Coord(0, 0)), Some(2));
+        
+        let east = &game.chunks[&Coord(1, 0)];
+        let mine = chunk::all_squares().find(|&square| east.mines.get(square)).unwrap();
+        game.act_as(Some(1));
+        game.touch(&[Coord::from(AbsoluteCoord { chunk: Coord(1, 0), square: mine })]);
+        assert_eq!(game.owner(Coord(1, 0)), Some(1));
+        
+        assert_eq!((game.chunks_won(), game.chunks_lost()), (1, 1));
+        assert_eq!(game.owner(Coord(0, 1)), None);
+        assert_eq!(game.score(), points(1, 1));
+    }
+    
+    #[test]
+    fn events() {
+        let mut game = Game::with_settings(3, Difficulty::Normal);
+        let events = game.subscribe();
+        
+        game.touch(&[Coord(4, 4)]);
+        while game.spread() {}
+        let opening: Vec<_> = events.try_iter().collect();
+        
+        let allocated: Vec<_> = opening.iter()
+            .filter_map(|&event| match event { Event::ChunkAllocated(coord) => Some(coord), _ => None })
+            .collect();
+        let revealed: Vec<_> = opening.iter()
+            .filter_map(|&event| match event { Event::SquareRevealed(coord) => Some(coord), _ => None })
+            .collect();
+        let clicked: usize = game.chunks.values()
+            .map(|live| chunk::all_squares().filter(|&square| live.clicked.get(square)).count())
+            .sum();
+        
+        assert_eq!(allocated.len(), game.allocated());
+        assert_eq!(revealed.len(), clicked);
+        // The clicked chunk and its neighbours are there before anything's revealed
+        assert_eq!(opening[9], Event::SquareRevealed(Coord(4, 4)));
+        
+        let origin = &game.chunks[&Coord(0, 0)];
+        let mine = chunk::all_squares().find(|&square| origin.mines.get(square)).unwrap();
+        let blank = chunk::all_squares().find(|&square| !origin.mines.get(square) && !origin.clicked.get(square)).unwrap();
+        let (mine, blank) = (Coord::<isize>::from(mine), Coord::<isize>::from(blank));
+        
+        game.toggle_flag(blank);
+        game.toggle_flag(blank);
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), [Event::FlagToggled(blank), Event::FlagToggled(blank)]);
+        
+        game.touch(&[mine]);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [Event::SquareRevealed(mine), Event::ChunkLost(Coord(0, 0)), Event::ScoreChanged(-LOSS_PENALTY)],
+        );
+        
+        // Finished chunks ignore input, so nothing more happens
+        game.touch(&[blank]);
+        game.toggle_flag(blank);
+        assert_eq!(events.try_iter().next(), None);
+    }
 }
@@ -0,0 +1,211 @@
+// Randomised checks over whole games. Besides clicks and flags, actions
+// can solve a chunk outright, so sequences finish chunks often enough to
+// exercise wins as well as losses.
+
+use super::*;
+use proptest::{prelude::*, collection::vec};
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Touch(Coord<isize>),
+    Flag(Coord<isize>),
+    // Flag every mine and click everything else
+    Solve(Coord<isize>),
+}
+
+fn apply(game: &mut Game, action: Action) {
+    match action {
+        Action::Touch(at) => {
+            let first = !game.opened;
+            game.touch(&[at]);
+            while game.spread() {}
+            if first { assert_opened(game, at); }
+        },
+        Action::Flag(at) => game.toggle_flag(at),
+        Action::Solve(coord) => for square in chunk::all_squares() {
+            let world = Coord::from(AbsoluteCoord { chunk: coord, square });
+            let mined = game.mines(coord).map(|mines| mines.get(square));
+            match (mined, game.get_chunk(coord).map(|chunk| chunk.view(square))) {
+                (Some(true), Some(SquareView::Unclicked)) => game.toggle_flag(world),
+                (Some(false), _) | (None, _) => apply(game, Action::Touch(world)),
+                _ => (),
+            }
+        },
+    }
+}
+
+// The first click is never a mine, nor next to one, flags or no flags
+fn assert_opened(game: &Game, click: Coord<isize>) {
+    for around in IndexIterSigned::self_and_adjacent(click) {
+        let AbsoluteCoord { chunk, square } = around.into();
+        assert!(!game.mines(chunk).unwrap().get(square), "mine at {:?} next to first click {:?}", around, click);
+    }
+    assert_eq!(game.square_view(click), Some(SquareView::Clicked(0)), "first click {:?}", click);
+}
+
+fn assert_consistent(game: &Game, history: &[Action]) {
+    let everywhere: Vec<_> = game.chunks.keys().chain(game.frozen.keys()).cloned().collect();
+    assert_consistent_in(game, &everywhere, history);
+}
+
+// Checks the given chunks, which are all that an action can change when
+// they're its chunks and their neighbours
+fn assert_consistent_in(game: &Game, coords: &[Coord<isize>], history: &[Action]) {
+    for (coord, chunk) in &game.chunks {
+        assert!(chunk.outcome().is_none(), "{:?} finished but live after {:?}", coord, history);
+        assert!(chunk.stage() != Stage::Blank);
+        if chunk.clicked != BitField::default() {
+            assert_eq!(chunk.stage(), Stage::Neighbored, "{:?} clicked before counting after {:?}", coord, history);
+        }
+    }
+
+    for &coord in coords {
+        let chunk = game.get_chunk(coord).unwrap();
+        let (mines, clicked, flags) = fields(chunk);
+        let outcome = chunk.outcome();
+        let exploded = chunk::all_squares().any(|square| mines.get(square) && clicked.get(square));
+        assert_eq!(outcome == Some(Outcome::Lost), exploded, "{:?} after {:?}", coord, history);
+
+        for square in chunk::all_squares() {
+            let world = Coord::from(AbsoluteCoord { chunk: coord, square });
+            let consistent = match chunk.view(square) {
+                SquareView::Clicked(count) => {
+                    let actual = IndexIterSigned::self_and_adjacent(world)
+                        .filter(|&around| {
+                            let AbsoluteCoord { chunk, square } = around.into();
+                            game.mines(chunk).unwrap().get(square)
+                        })
+                        .count();
+                    clicked.get(square) && !mines.get(square) && count as usize == actual
+                },
+                SquareView::Penalty => mines.get(square) && (clicked.get(square) || outcome == Some(Outcome::Lost)),
+                SquareView::Points => !clicked.get(square) && outcome == Some(Outcome::Won),
+                SquareView::Flagged => flags.get(square) && !clicked.get(square) && outcome != Some(Outcome::Won),
+                SquareView::Unclicked => !clicked.get(square) && !flags.get(square),
+            };
+            assert!(consistent, "{:?} shows {:?} after {:?}", world, chunk.view(square), history);
+
+            // Cascades open everything around a blank square, short of
+            // chunks that were lost before they got there
+            if let SquareView::Clicked(0) = chunk.view(square) {
+                for around in IndexIterSigned::self_and_adjacent(world) {
+                    let AbsoluteCoord { chunk, square } = around.into();
+                    let chunk = game.get_chunk(chunk).unwrap();
+                    let open = match chunk.view(square) {
+                        SquareView::Clicked(_) => true,
+                        _ => chunk.outcome() == Some(Outcome::Lost),
+                    };
+                    assert!(open, "{:?} next to blank {:?} left shut after {:?}", around, world, history);
+                }
+            }
+        }
+    }
+}
+
+// The chunks `events` changed, and those around them
+fn affected(game: &Game, events: impl Iterator<Item=Event>) -> Vec<Coord<isize>> {
+    let mut coords: Vec<_> = events
+        .filter_map(|event| match event {
+            Event::SquareRevealed(world) | Event::FlagToggled(world) => Some(AbsoluteCoord::from(world).chunk),
+            Event::ChunkAllocated(coord) | Event::ChunkWon(coord) | Event::ChunkLost(coord) => Some(coord),
+            Event::ScoreChanged(_) => None,
+        })
+        .flat_map(IndexIterSigned::self_and_adjacent)
+        .filter(|&coord| game.get_chunk(coord).is_some())
+        .collect();
+    coords.sort_by_key(|&Coord(x, y)| (x, y));
+    coords.dedup();
+    coords
+}
+
+// Mines, clicks and flags
+fn fields(chunk: ChunkRef) -> (BitField, BitField, BitField) {
+    match chunk {
+        ChunkRef::Live(live) => (live.mines, live.clicked, live.flags),
+        ChunkRef::Frozen(frozen) => (frozen.mines, frozen.clicked, frozen.flags),
+    }
+}
+
+fn action() -> impl Strategy<Value=Action> {
+    let at = (-24isize..24, -24isize..24).prop_map(|(x, y)| Coord(x, y));
+    prop_oneof![
+        4 => at.clone().prop_map(Action::Touch),
+        2 => at.prop_map(Action::Flag),
+        1 => (-3isize..3, -3isize..3).prop_map(|(x, y)| Action::Solve(Coord(x, y))),
+    ]
+}
+
+#[test]
+fn transition_sequences() {
+    const DEPTH: u32 = 3;
+    const SEED: u64 = 5;
+
+    let mut probe = Game::with_settings(SEED, Difficulty::Normal);
+    probe.touch(&[Coord(3, 3)]);
+    let mines = probe.mines(Coord(0, 0)).unwrap();
+    let mine = Coord::from(chunk::all_squares().find(|&square| mines.get(square)).unwrap());
+
+    let squares = [Coord(3, 3), mine, Coord(9, 2)];
+    let mut actions: Vec<_> = squares.iter().map(|&at| Action::Touch(at)).collect();
+    actions.extend(squares.iter().map(|&at| Action::Flag(at)));
+    actions.extend(vec![Action::Solve(Coord(0, 0)), Action::Solve(Coord(1, 0))]);
+
+    for sequence in 0..actions.len().pow(DEPTH) {
+        let mut game = Game::with_settings(SEED, Difficulty::Normal);
+        let mut history = Vec::new();
+
+        for step in 0..DEPTH {
+            let action = actions[sequence / actions.len().pow(step) % actions.len()];
+            history.push(action);
+            apply(&mut game, action);
+            assert_consistent(&game, &history);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn outcomes_counted_once(
+        seed: u64,
+        difficulty in prop::sample::select(Difficulty::ALL.to_vec()),
+        actions in vec(action(), 1..40),
+    ) {
+        let mut game = Game::with_settings(seed, difficulty);
+        let events = game.subscribe();
+
+        for (step, &action) in actions.iter().enumerate() {
+            let finished: Vec<_> = game.frozen
+                .keys()
+                .map(|&coord| (coord, region::encode(game.get_chunk(coord).unwrap()).to_vec()))
+                .collect();
+            let (won, lost) = (game.chunks_won(), game.chunks_lost());
+
+            apply(&mut game, action);
+
+            // Finished chunks ignore input
+            for (coord, bytes) in finished {
+                prop_assert_eq!(region::encode(game.get_chunk(coord).unwrap()).to_vec(), bytes, "{:?}", &actions[..=step]);
+            }
+
+            let count = |outcome| game.frozen.values().filter(|frozen| frozen.outcome == outcome).count() as u64;
+            prop_assert_eq!(game.chunks_won(), count(Outcome::Won));
+            prop_assert_eq!(game.chunks_lost(), count(Outcome::Lost));
+            prop_assert!(game.chunks_won() >= won && game.chunks_lost() >= lost);
+            prop_assert!(game.chunks.values().all(|chunk| chunk.outcome().is_none()));
+            assert_consistent_in(&game, &affected(&game, events.try_iter()), &actions[..=step]);
+        }
+        assert_consistent(&game, &actions);
+    }
+
+    #[test]
+    fn absolute_coord_round_trip(
+        x in prop_oneof![any::<isize>(), -100isize..100],
+        y in prop_oneof![any::<isize>(), -100isize..100],
+    ) {
+        let absolute = AbsoluteCoord::from(Coord(x, y));
+        prop_assert!(absolute.square.0 < chunk::DIMENSION && absolute.square.1 < chunk::DIMENSION);
+        prop_assert_eq!(Coord::from(absolute), Coord(x, y));
+    }
+}
@@ -0,0 +1,514 @@
+// Chunks paged out of memory, grouped into fixed-size region files of
+// REGION_DIMENSION² slots so neighbouring chunks share a file.
+
+use super::{
+    ChunkRef,
+    chunk::{
+        Chunk, Difficulty, Stage, Outcome,
+        field::{BitField, NybbleField, DIMENSION, NYBBLE_BYTES},
+        frozen::{FrozenChunk, Halo, HALO_BYTES},
+    },
+};
+use crate::aux::{coord::Coord, DivFloorSignedExt, ModuloSignedExt};
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub const REGION_DIMENSION: isize = 32;
+const SLOTS: usize = (REGION_DIMENSION * REGION_DIMENSION) as usize;
+const WORDS: usize = SLOTS / 64;
+
+const MAGIC: &[u8; 8] = b"MSWREG01";
+const WORLD_FILE: &str = "world";
+pub const SLOT_BYTES: usize = 1 + 3 * DIMENSION + NYBBLE_BYTES;
+
+// Everything about a game besides its chunks that it needs to go on from
+// where it was left. Kept next to the region files, as lines of `key value`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct World {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub chunks_won: u64,
+    pub chunks_lost: u64,
+    pub opened: bool,
+    pub bookmarks: BTreeMap<String, Coord<isize>>,
+}
+
+impl World {
+    // Bookmark names go last, since they can have spaces in them
+    fn encode(&self) -> String {
+        let mut text = format!(
+            "seed {}\ndifficulty {}\nwon {}\nlost {}\nopened {}\n",
+            self.seed, self.difficulty.name(), self.chunks_won, self.chunks_lost, self.opened,
+        );
+        for (name, Coord(x, y)) in self.bookmarks.iter() {
+            text += &format!("bookmark {} {} {}\n", x, y, name);
+        }
+        text
+    }
+
+    fn decode(text: &str) -> Result<Self, String> {
+        let mut world = World::default();
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap_or("");
+            let value = words.next().ok_or_else(|| format!("Missing value for {}", key))?;
+            let invalid = || format!("Invalid {}: {}", key, value);
+
+            match key {
+                "seed"       => world.seed        = value.parse().map_err(|_| invalid())?,
+                "difficulty" => world.difficulty  = Difficulty::from_name(value).ok_or_else(invalid)?,
+                "won"        => world.chunks_won  = value.parse().map_err(|_| invalid())?,
+                "lost"       => world.chunks_lost = value.parse().map_err(|_| invalid())?,
+                "opened"     => world.opened      = value.parse().map_err(|_| invalid())?,
+                "bookmark" => {
+                    let x = value.parse().map_err(|_| invalid())?;
+                    let y = words.next().and_then(|y| y.parse().ok()).ok_or_else(invalid)?;
+                    let name = words.by_ref().collect::<Vec<_>>().join(" ");
+                    if name.is_empty() { return Err("Missing bookmark name".to_owned()); }
+                    world.bookmarks.insert(name, Coord(x, y));
+                },
+                _ => return Err(format!("Unknown key {}", key)),
+            }
+            if let Some(extra) = words.next() {
+                return Err(format!("Unexpected {}", extra));
+            }
+        }
+
+        Ok(world)
+    }
+}
+
+pub enum Slot {
+    Live(Chunk),
+    Frozen(FrozenChunk),
+}
+
+impl Slot {
+    pub fn as_ref(&self) -> ChunkRef<'_> {
+        match self {
+            Slot::Live(chunk) => ChunkRef::Live(chunk),
+            Slot::Frozen(chunk) => ChunkRef::Frozen(chunk),
+        }
+    }
+}
+
+// Which slots of a region hold a chunk, and which of those are unfinished
+#[derive(Default)]
+struct Occupancy {
+    stored: [u64; WORDS],
+    unfinished: [u64; WORDS],
+}
+
+pub struct RegionStore {
+    dir: PathBuf,
+    temporary: bool,
+    regions: hashbrown::HashMap<Coord<isize>, Occupancy>,
+    world: Option<World>,
+}
+
+impl RegionStore {
+    // Picks up whatever world an earlier run left in `dir`
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut regions = hashbrown::HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let region = match path.file_name().and_then(|name| name.to_str()).and_then(parse_name) {
+                Some(region) => region,
+                None => continue,
+            };
+            regions.insert(region, read_occupancy(&path)?);
+        }
+
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let world = match fs::read_to_string(dir.join(WORLD_FILE)) {
+            Ok(text) => Some(World::decode(&text).map_err(|error| invalid(format!("{} in world file", error)))?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+        // Without the seed they were generated from, stored neighbour
+        // counts wouldn't match the chunks generated around them
+        if world.is_none() && !regions.is_empty() {
+            return Err(invalid(format!("{} has region files but no world file", dir.display())));
+        }
+
+        Ok(RegionStore { dir, temporary: false, regions, world })
+    }
+
+    // Removed again when dropped
+    pub fn temporary() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("minesweepfinity-{}-{}", process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let dir = env::temp_dir().join(name);
+        let mut store = RegionStore::open(dir)?;
+        store.temporary = true;
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    // The world the stored chunks belong to, if any have been saved
+    pub fn world(&self) -> Option<&World> {
+        self.world.as_ref()
+    }
+
+    // Replaced whole, so a crash leaves either the old file or the new one
+    pub fn save_world(&mut self, world: World) -> io::Result<()> {
+        let staging = self.dir.join(format!("{}.new", WORLD_FILE));
+        fs::write(&staging, world.encode())?;
+        fs::rename(&staging, self.dir.join(WORLD_FILE))?;
+        self.world = Some(world);
+        Ok(())
+    }
+
+    pub fn contains(&self, chunk: Coord<isize>) -> bool {
+        let (region, slot) = locate(chunk);
+        self.regions.get(&region).is_some_and(|occupancy| get_bit(&occupancy.stored, slot))
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions
+            .values()
+            .map(|occupancy| occupancy.stored.iter().map(|word| word.count_ones() as usize).sum::<usize>())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn unfinished<'a>(&'a self) -> impl Iterator<Item=Coord<isize>> + 'a {
+        self.regions.iter().flat_map(|(&region, occupancy)| {
+            (0..SLOTS)
+                .filter(move |&slot| get_bit(&occupancy.unfinished, slot))
+                .map(move |slot| {
+                    let local = Coord((slot % REGION_DIMENSION as usize) as isize, (slot / REGION_DIMENSION as usize) as isize);
+                    region * Coord::squared(REGION_DIMENSION) + local
+                })
+        })
+    }
+
+    pub fn save(&mut self, chunk: Coord<isize>, data: ChunkRef) -> io::Result<()> {
+        let (region, slot) = locate(chunk);
+        let path = self.path(region);
+        let exists = path.exists();
+
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        if !exists {
+            file.write_all(MAGIC)?;
+            file.set_len(slot_offset(SLOTS))?;
+        }
+        file.seek(SeekFrom::Start(slot_offset(slot)))?;
+        file.write_all(&encode(data))?;
+
+        let occupancy = self.regions.entry(region).or_insert_with(Occupancy::default);
+        set_bit(&mut occupancy.stored, slot, true);
+        set_bit(&mut occupancy.unfinished, slot, data.unfinished());
+        Ok(())
+    }
+
+    // Reads a chunk back; the store forgets it so the caller now owns it
+    pub fn take(&mut self, chunk: Coord<isize>) -> io::Result<Option<Slot>> {
+        if !self.contains(chunk) { return Ok(None); }
+
+        let (region, slot) = locate(chunk);
+        let mut file = File::open(self.path(region))?;
+        let mut slot_data = [0; SLOT_BYTES];
+        file.seek(SeekFrom::Start(slot_offset(slot)))?;
+        file.read_exact(&mut slot_data)?;
+
+        let data = decode(&slot_data).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let occupancy = self.regions.get_mut(&region).unwrap();
+        set_bit(&mut occupancy.stored, slot, false);
+        set_bit(&mut occupancy.unfinished, slot, false);
+        Ok(data)
+    }
+
+    fn path(&self, Coord(x, y): Coord<isize>) -> PathBuf {
+        self.dir.join(format!("r.{}.{}.bin", x, y))
+    }
+}
+
+impl Drop for RegionStore {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn parse_name(name: &str) -> Option<Coord<isize>> {
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".bin")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    if parts.next().is_some() { return None; }
+    Some(Coord(x, y))
+}
+
+// Rebuilt from the status byte at the start of each slot
+fn read_occupancy(path: &Path) -> io::Result<Occupancy> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    if bytes.len() != slot_offset(SLOTS) as usize || !bytes.starts_with(MAGIC) {
+        let message = format!("{} is not a region file", path.display());
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    let mut occupancy = Occupancy::default();
+    for slot in 0..SLOTS {
+        let status = bytes[slot_offset(slot) as usize];
+        set_bit(&mut occupancy.stored, slot, status != 0);
+        set_bit(&mut occupancy.unfinished, slot, status == 2);
+    }
+    Ok(occupancy)
+}
+
+fn locate(chunk: Coord<isize>) -> (Coord<isize>, usize) {
+    let region = chunk.map(|x| DivFloorSignedExt::div_floor(x, REGION_DIMENSION));
+    let Coord(x, y) = chunk.map(|x| x.modulo(REGION_DIMENSION) as usize);
+    (region, y * REGION_DIMENSION as usize + x)
+}
+
+fn slot_offset(slot: usize) -> u64 {
+    (MAGIC.len() + slot * SLOT_BYTES) as u64
+}
+
+fn get_bit(words: &[u64; WORDS], bit: usize) -> bool {
+    words[bit / 64] & (1 << (bit % 64)) != 0
+}
+
+fn set_bit(words: &mut [u64; WORDS], bit: usize, value: bool) {
+    if value {
+        words[bit / 64] |= 1 << (bit % 64);
+    } else {
+        words[bit / 64] &= !(1 << (bit % 64));
+    }
+}
+
+// Live chunks end with their neighbour counts and frozen ones with their halo
+pub fn encode(chunk: ChunkRef) -> [u8; SLOT_BYTES] {
+    let mut bytes = [0; SLOT_BYTES];
+
+    bytes[0] = match (chunk.stage(), chunk.outcome()) {
+        (_, Some(Outcome::Won))     => 3,
+        (_, Some(Outcome::Lost))    => 4,
+        (Stage::Blank,      None) => 0,
+        (Stage::Enmined,    None) => 1,
+        (Stage::Neighbored, None) => 2,
+    };
+
+    let fields = match chunk {
+        ChunkRef::Live(live) => [live.mines, live.clicked, live.flags],
+        ChunkRef::Frozen(frozen) => [frozen.mines, frozen.clicked, frozen.flags],
+    };
+    for (index, field) in fields.iter().enumerate() {
+        bytes[1 + index * DIMENSION..][..DIMENSION].copy_from_slice(&field.to_bytes());
+    }
+
+    let tail = &mut bytes[1 + 3 * DIMENSION..];
+    match chunk {
+        ChunkRef::Live(live) => tail.copy_from_slice(&live.neighbors.to_bytes()),
+        ChunkRef::Frozen(frozen) => tail[..HALO_BYTES].copy_from_slice(&frozen.halo.to_bytes()),
+    }
+
+    bytes
+}
+
+// An empty slot decodes to `None`
+pub fn decode(bytes: &[u8]) -> Result<Option<Slot>, &'static str> {
+    if bytes.len() != SLOT_BYTES { return Err("Wrong slot size"); }
+
+    let (stage, outcome) = match bytes[0] {
+        0 => return Ok(None),
+        1 => (Stage::Enmined,    None),
+        2 => (Stage::Neighbored, None),
+        3 => (Stage::Neighbored, Some(Outcome::Won)),
+        4 => (Stage::Neighbored, Some(Outcome::Lost)),
+        _ => return Err("Invalid chunk status"),
+    };
+
+    let field = |index: usize| {
+        let mut row_bytes = [0; DIMENSION];
+        row_bytes.copy_from_slice(&bytes[1 + index * DIMENSION..][..DIMENSION]);
+        BitField::from_bytes(row_bytes)
+    };
+
+    let tail = &bytes[1 + 3 * DIMENSION..];
+
+    if let Some(outcome) = outcome {
+        let mut halo_bytes = [0; HALO_BYTES];
+        halo_bytes.copy_from_slice(&tail[..HALO_BYTES]);
+
+        if tail[HALO_BYTES..].iter().any(|&byte| byte != 0) || halo_bytes[4] > 0b1111 {
+            return Err("Invalid halo");
+        }
+
+        return Ok(Some(Slot::Frozen(FrozenChunk {
+            outcome,
+            mines: field(0),
+            clicked: field(1),
+            flags: field(2),
+            halo: Halo::from_bytes(halo_bytes),
+        })));
+    }
+
+    let mut neighbor_bytes = [0; NYBBLE_BYTES];
+    neighbor_bytes.copy_from_slice(tail);
+    let neighbors = NybbleField::from_bytes(neighbor_bytes);
+
+    if super::chunk::all_squares().any(|square| neighbors.get(square) > 8) {
+        return Err("Invalid neighbor count");
+    }
+
+    Ok(Some(Slot::Live(Chunk::restore(stage, field(0), field(1), field(2), neighbors))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::chunk::Difficulty;
+    use rand::XorShiftRng;
+    use proptest::{prelude::*, collection::vec};
+
+    #[test]
+    fn round_trip() {
+        let mut chunk = Chunk::with_mines(&mut XorShiftRng::new_unseeded(), Difficulty::Hard);
+        chunk.clicked.set(Coord(1, 2));
+        chunk.flags.set(Coord(7, 0));
+        let mut neighbors = NybbleField::default();
+        neighbors.set(Coord(3, 3), 8);
+        chunk.set_neighbors(neighbors);
+
+        let bytes = encode(ChunkRef::Live(&chunk));
+        let decoded = decode(&bytes).unwrap().unwrap();
+        assert_eq!(encode(decoded.as_ref())[..], bytes[..]);
+
+        chunk.finish(Outcome::Lost);
+        let halo = Halo::from_bytes([1, 2, 3, 4, 0b1010]);
+        let frozen = encode(ChunkRef::Frozen(&FrozenChunk::freeze(&chunk, halo)));
+        let decoded = decode(&frozen).unwrap().unwrap();
+        assert_eq!(encode(decoded.as_ref())[..], frozen[..]);
+
+        assert!(decode(&[0; SLOT_BYTES]).unwrap().is_none());
+        assert!(decode(&bytes[1..]).is_err());
+        let mut corrupt = bytes;
+        corrupt[0] = 9;
+        assert!(decode(&corrupt).is_err());
+        let mut corrupt = frozen;
+        corrupt[SLOT_BYTES - 1] = 1;
+        assert!(decode(&corrupt).is_err());
+    }
+
+    #[test]
+    fn save_and_take() {
+        let mut store = RegionStore::temporary().unwrap();
+        let mut chunk = Chunk::with_mines(&mut XorShiftRng::new_unseeded(), Difficulty::Normal);
+        chunk.set_neighbors(NybbleField::default());
+        let coords = [Coord(0, 0), Coord(-1, -1), Coord(31, 31), Coord(32, -33), Coord(-100, 7)];
+
+        for &coord in coords.iter() {
+            chunk.clicked.set(Coord(coord.0.modulo(8) as usize, 0));
+            store.save(coord, ChunkRef::Live(&chunk)).unwrap();
+        }
+        assert_eq!(store.len(), coords.len());
+        assert_eq!(store.unfinished().count(), coords.len());
+        assert!(!store.contains(Coord(1, 0)));
+
+        let mut unfinished: Vec<_> = store.unfinished().collect();
+        unfinished.sort_by_key(|&Coord(x, y)| (x, y));
+        let mut expected = coords.to_vec();
+        expected.sort_by_key(|&Coord(x, y)| (x, y));
+        assert_eq!(unfinished, expected);
+
+        match store.take(Coord(32, -33)).unwrap() {
+            Some(Slot::Live(taken)) => assert!(taken.clicked.get(Coord(0, 0))),
+            _ => panic!("expected a live chunk"),
+        }
+        assert!(store.take(Coord(32, -33)).unwrap().is_none());
+        assert_eq!(store.len(), coords.len() - 1);
+    }
+
+    #[test]
+    fn reopen() {
+        let mut store = RegionStore::temporary().unwrap();
+        let mut chunk = Chunk::with_mines(&mut XorShiftRng::new_unseeded(), Difficulty::Normal);
+        chunk.set_neighbors(NybbleField::default());
+        store.save(Coord(3, -40), ChunkRef::Live(&chunk)).unwrap();
+        chunk.finish(Outcome::Won);
+        let frozen = FrozenChunk::freeze(&chunk, Halo::default());
+        store.save(Coord(-70, 5), ChunkRef::Frozen(&frozen)).unwrap();
+        fs::write(store.dir.join("notes.txt"), "not a region").unwrap();
+
+        // Chunks alone can't be picked up without knowing their world
+        assert!(RegionStore::open(store.dir.clone()).is_err());
+        let mut world = World { seed: 12, difficulty: Difficulty::Hard, chunks_won: 1, chunks_lost: 0, opened: true, ..World::default() };
+        world.bookmarks.insert("base camp".to_owned(), Coord(-5, 900));
+        world.bookmarks.insert("x".to_owned(), Coord(isize::MIN, 0));
+        store.save_world(world.clone()).unwrap();
+
+        let mut reopened = RegionStore::open(store.dir.clone()).unwrap();
+        assert_eq!(reopened.world(), Some(&world));
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.unfinished().collect::<Vec<_>>(), vec![Coord(3, -40)]);
+        match reopened.take(Coord(-70, 5)).unwrap() {
+            Some(Slot::Frozen(taken)) => assert_eq!(taken.outcome, Outcome::Won),
+            _ => panic!("expected a frozen chunk"),
+        }
+
+        fs::write(store.dir.join("r.1.1.bin"), "truncated").unwrap();
+        assert!(RegionStore::open(store.dir.clone()).is_err());
+        fs::remove_file(store.dir.join("r.1.1.bin")).unwrap();
+        fs::write(store.dir.join(WORLD_FILE), "seed 12\ndifficulty extreme\n").unwrap();
+        assert!(RegionStore::open(store.dir.clone()).is_err());
+    }
+
+    // Mostly slots that are nearly valid, since random bytes rarely get
+    // past the status byte
+    fn slot_bytes() -> impl Strategy<Value=Vec<u8>> {
+        let counts = vec((0u8..10, 0u8..10).prop_map(|(low, high)| low | high << 4), NYBBLE_BYTES);
+        let halo = (vec(any::<u8>(), HALO_BYTES), 0u8..20).prop_map(|(mut halo, corners)| {
+            halo[HALO_BYTES - 1] = corners;
+            halo.resize(NYBBLE_BYTES, 0);
+            halo
+        });
+
+        prop_oneof![
+            vec(any::<u8>(), SLOT_BYTES),
+            (0u8..6, vec(any::<u8>(), 3 * DIMENSION), prop_oneof![counts, halo]).prop_map(|(status, fields, tail)| {
+                let mut bytes = vec![status];
+                bytes.extend(fields);
+                bytes.extend(tail);
+                bytes
+            }),
+        ]
+    }
+
+    proptest! {
+        // Whatever the parser accepts is exactly what the writer produces
+        #[test]
+        fn decode_round_trips(bytes in slot_bytes()) {
+            if let Ok(Some(slot)) = decode(&bytes) {
+                prop_assert_eq!(&encode(slot.as_ref())[..], &bytes[..]);
+            }
+        }
+    }
+}
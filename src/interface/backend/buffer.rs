@@ -0,0 +1,174 @@
+use super::Style;
+use crate::aux::coord::Coord;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Option<Style>,
+}
+
+pub const BLANK: Cell = Cell { ch: ' ', style: None };
+
+// Cells of the same style next to each other on a row, sent in one go
+#[derive(PartialEq, Debug)]
+pub struct Run {
+    pub at: Coord<usize>,
+    pub text: String,
+    pub style: Option<Style>,
+}
+
+// What the screen should look like next to what the terminal was last sent,
+// so a refresh only sends the cells that differ
+#[derive(Default)]
+pub struct Buffer {
+    size: Coord<usize>,
+    cells: Vec<Cell>,
+    // `None` where the terminal's contents aren't known
+    shown: Vec<Option<Cell>>,
+}
+
+impl Buffer {
+    pub fn size(&self) -> Coord<usize> { self.size }
+
+    // What the terminal was last sent for a cell
+    pub fn shown(&self, Coord(x, y): Coord<usize>) -> Option<Cell> {
+        self.shown[y * self.size.0 + x]
+    }
+
+    // Matches the buffer to the terminal, which is expected to be cleared if
+    // this returns true because its size changed
+    pub fn fit(&mut self, size: Coord<usize>) -> bool {
+        if size == self.size { return false; }
+
+        self.size = size;
+        self.cells = vec![BLANK; size.0 * size.1];
+        self.shown = vec![Some(BLANK); size.0 * size.1];
+        true
+    }
+
+    // Sends everything again on the next refresh, say after colors change
+    pub fn invalidate(&mut self) {
+        for shown in self.shown.iter_mut() { *shown = None; }
+    }
+
+    pub fn put_str(&mut self, Coord(x, y): Coord<usize>, text: &str, style: Style) {
+        if y >= self.size.1 { return; }
+
+        for (offset, ch) in text.chars().enumerate() {
+            if x + offset >= self.size.0 { break; }
+            self.cells[y * self.size.0 + x + offset] = Cell { ch, style: Some(style) };
+        }
+    }
+
+    // Moves the top `rows` rows so content at `delta` lands on the origin.
+    // Cells with nothing to move into them are left as they were.
+    pub fn shift(&mut self, delta: Coord<isize>, rows: usize) {
+        let rows = rows.min(self.size.1);
+        shift(&mut self.cells, self.size.0, delta, rows, None);
+    }
+
+    // How far a terminal with scrolling regions can scroll its top `rows`
+    // rows itself to match a shift, if it can
+    pub fn scrolling(&self, Coord(dx, dy): Coord<isize>, rows: usize) -> Option<isize> {
+        let rows = rows.min(self.size.1);
+        if dx != 0 || dy == 0 || dy.unsigned_abs() >= rows { None } else { Some(dy) }
+    }
+
+    // The terminal scrolled its top `rows` rows up by `dy` by itself,
+    // blanking the ones scrolled in
+    pub fn scrolled(&mut self, dy: isize, rows: usize) {
+        let rows = rows.min(self.size.1);
+        shift(&mut self.shown, self.size.0, Coord(0, dy), rows, Some(Some(BLANK)));
+    }
+
+    // Everything that needs sending, which counts as shown from then on
+    pub fn changes(&mut self) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        let mut next = None;
+
+        for (index, (&cell, shown)) in self.cells.iter().zip(self.shown.iter_mut()).enumerate() {
+            if *shown == Some(cell) { continue; }
+            *shown = Some(cell);
+
+            let at = Coord(index % self.size.0, index / self.size.0);
+            match runs.last_mut() {
+                Some(run) if next == Some(at) && run.style == cell.style => run.text.push(cell.ch),
+                _ => runs.push(Run { at, text: cell.ch.to_string(), style: cell.style }),
+            }
+            next = if at.0 + 1 < self.size.0 { Some(Coord(at.0 + 1, at.1)) } else { None };
+        }
+
+        runs
+    }
+}
+
+fn shift<T: Copy>(cells: &mut [T], width: usize, Coord(dx, dy): Coord<isize>, rows: usize, fill: Option<T>) {
+    let before = cells.to_vec();
+    let Coord(width, height) = Coord(width as isize, rows as isize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            let Coord(source_x, source_y) = Coord(x + dx, y + dy);
+
+            if source_x < 0 || source_x >= width || source_y < 0 || source_y >= height {
+                if let Some(fill) = fill { cells[index] = fill; }
+                continue;
+            }
+
+            cells[index] = before[(source_y * width + source_x) as usize];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::backend::Paint;
+
+    fn texts(runs: &[Run]) -> Vec<(Coord<usize>, &str)> {
+        runs.iter().map(|run| (run.at, run.text.as_str())).collect()
+    }
+
+    #[test]
+    fn only_changes_are_sent() {
+        let mut buffer = Buffer::default();
+        buffer.fit(Coord(4, 3));
+        assert_eq!(buffer.changes(), []);
+
+        let (plain, bold) = (Style::from(Paint::Checker(0)), Style::from(Paint::Points));
+        buffer.put_str(Coord(0, 0), "abcd", plain);
+        buffer.put_str(Coord(2, 0), "x", bold);
+        buffer.put_str(Coord(3, 1), "ef", plain);
+        assert_eq!(texts(&buffer.changes()), [(Coord(0, 0), "ab"), (Coord(2, 0), "x"), (Coord(3, 0), "d"), (Coord(3, 1), "e")]);
+
+        // Drawing over something with the same thing sends nothing
+        buffer.put_str(Coord(0, 0), "zb", plain);
+        buffer.put_str(Coord(0, 0), "a", plain);
+        assert_eq!(buffer.changes(), []);
+
+        buffer.invalidate();
+        assert_eq!(buffer.changes().len(), 6);
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut buffer = Buffer::default();
+        buffer.fit(Coord(2, 3));
+        let style = Style::from(Paint::Overlay);
+        for (y, row) in ["ab", "cd", "ef"].iter().enumerate() {
+            buffer.put_str(Coord(0, y), row, style);
+        }
+        buffer.changes();
+
+        // Only the row scrolled into view needs sending
+        buffer.shift(Coord(0, 1), 2);
+        buffer.scrolled(1, 2);
+        buffer.put_str(Coord(0, 1), "gh", style);
+        assert_eq!(texts(&buffer.changes()), [(Coord(0, 1), "gh")]);
+
+        // Without the terminal's help, shifted rows are sent again
+        buffer.shift(Coord(0, -1), 2);
+        assert_eq!(texts(&buffer.changes()), [(Coord(0, 1), "cd")]);
+    }
+}
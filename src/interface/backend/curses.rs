@@ -0,0 +1,286 @@
+use super::{Renderer, InputSource, Event, Key, Mouse, MouseKind, Button, Paint, Style, buffer::Buffer};
+use crate::{
+    aux::coord::Coord,
+    interface::theme::{self, Theme},
+};
+
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use ncurses::{self, COLOR_PAIR};
+
+const CHECKER_1: i16 = 10;
+const OVERLAY_1: i16 = 20;
+const POINTS:    i16 =  8;
+const PENALTY:   i16 =  9;
+const DIGITS:    i16 = 30;
+const PLAYERS:   i16 = 40;
+
+const CLOCK_TICK_MS: i32 = 1000;
+
+// xterm mouse tracking mode that reports all motion, for dragging and hover
+const MOTION_TRACKING_ON:  &str = "\x1b[?1003h";
+const MOTION_TRACKING_OFF: &str = "\x1b[?1003l";
+
+const KEY_ESCAPE:  i32 = 27;
+const KEY_NEWLINE: i32 = '\n' as i32;
+const KEY_DELETE:  i32 = 127;
+const KEY_EOT:     i32 = 4;
+
+// Only hands curses the cells that changed, each run of them under a
+// single attribute change
+pub struct Screen {
+    mono: bool,
+    buffer: Buffer,
+    // What the terminal's palette held in each slot a theme redefined
+    original_colors: Vec<(i16, (i16, i16, i16))>,
+}
+
+pub struct Input {
+    tick_ms: i32,
+}
+
+pub fn init(mono: bool) -> (Screen, Input) {
+    use ncurses::*;
+
+    setlocale(LcCategory::all, "");
+    let window = initscr();
+
+    cbreak();
+    keypad(window, true);
+    // Lets curses scroll the terminal when rows have only moved
+    idlok(window, true);
+    mousemask((ALL_MOUSE_EVENTS | REPORT_MOUSE_POSITION) as mmask_t, None);
+    mouseinterval(0);
+    timeout(CLOCK_TICK_MS);
+    set_motion_tracking(MOTION_TRACKING_ON);
+    noecho();
+    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+    let mut screen = Screen { mono: mono || !has_colors(), buffer: Buffer::default(), original_colors: Vec::new() };
+    screen.fit();
+
+    if !screen.mono {
+        start_color();
+        screen.set_theme(&Theme::default());
+    }
+
+    (screen, Input { tick_ms: CLOCK_TICK_MS })
+}
+
+impl Screen {
+    // Matches the buffer to the terminal, which may have been resized
+    fn fit(&mut self) {
+        let size = self.size();
+        if self.buffer.fit(size) {
+            ncurses::clear();
+        }
+    }
+}
+
+impl Renderer for Screen {
+    fn size(&self) -> Coord<usize> {
+        Coord(ncurses::COLS() as usize, ncurses::LINES() as usize)
+    }
+
+    fn monochrome(&self) -> bool { self.mono }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        if self.mono { return; }
+
+        let mut palette = theme::Palette::new(color_depth());
+        let mut init_pair = |paint: Paint, theme::Pair { fg, bg }| {
+            ncurses::init_pair(pair(paint), palette.index(fg), palette.index(bg));
+        };
+
+        init_pair(Paint::Checker(0), theme.checker[0]);
+        init_pair(Paint::Checker(1), theme.checker[1]);
+        init_pair(Paint::Overlay,    theme.overlay);
+        init_pair(Paint::Points,     theme.points);
+        init_pair(Paint::Penalty,    theme.penalty);
+        for (digit, &fg) in theme.digits.iter().enumerate() {
+            init_pair(Paint::Digit(digit as u8), theme::Pair { fg, bg: theme.clicked.bg });
+        }
+        for (slot, &colors) in theme.players.iter().enumerate() {
+            init_pair(Paint::Player(slot as u8), colors);
+        }
+
+        for (slot, (r, g, b)) in palette.custom_colors() {
+            if !self.original_colors.iter().any(|&(saved, _)| saved == slot) {
+                let (mut r, mut g, mut b) = (0, 0, 0);
+                ncurses::color_content(slot, &mut r, &mut g, &mut b);
+                self.original_colors.push((slot, (r, g, b)));
+            }
+
+            let scale = |channel: u8| (channel as i32 * 1000 / 255) as i16;
+            ncurses::init_color(slot, scale(r), scale(g), scale(b));
+        }
+    }
+
+    fn put_str(&mut self, at: Coord<usize>, text: &str, style: Style) {
+        self.fit();
+        self.buffer.put_str(at, text, style);
+    }
+
+    fn shift(&mut self, delta: Coord<isize>, rows: usize) {
+        self.fit();
+        self.buffer.shift(delta, rows);
+    }
+
+    fn refresh(&mut self) {
+        self.fit();
+
+        for run in self.buffer.changes() {
+            let mut attributes = match run.style {
+                Some(style) if self.mono => mono_attributes(style.paint),
+                Some(style) => COLOR_PAIR(pair(style.paint)),
+                None => ncurses::A_NORMAL(),
+            };
+            if run.style.is_some_and(|style| style.underline) { attributes |= ncurses::A_UNDERLINE(); }
+
+            let Coord(x, y) = run.at;
+            ncurses::attron(attributes);
+            ncurses::mvaddstr(y as i32, x as i32, &run.text);
+            ncurses::attroff(attributes);
+        }
+
+        ncurses::refresh();
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        for &(slot, (r, g, b)) in self.original_colors.iter() {
+            ncurses::init_color(slot, r, g, b);
+        }
+        set_motion_tracking(MOTION_TRACKING_OFF);
+        ncurses::endwin();
+    }
+}
+
+impl InputSource for Input {
+    fn next_event(&mut self) -> Event {
+        loop {
+            match ncurses::getch() {
+                ncurses::ERR => return Event::Tick,
+                input => if let Some(event) = decode(input) { return event; },
+            }
+        }
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        ncurses::timeout(0);
+        let event = loop {
+            match ncurses::getch() {
+                ncurses::ERR => break None,
+                input => if let Some(event) = decode(input) { break Some(event); },
+            }
+        };
+        ncurses::timeout(self.tick_ms);
+        event
+    }
+
+    fn set_tick(&mut self, tick: Duration) {
+        self.tick_ms = tick.as_millis() as i32;
+        ncurses::timeout(self.tick_ms);
+    }
+}
+
+fn decode(input: i32) -> Option<Event> {
+    use ncurses::*;
+
+    let key = match input {
+        KEY_MOUSE  => return decode_mouse().map(Event::Mouse),
+        KEY_RESIZE => return Some(Event::Resize),
+        // Ctrl-D, so the terminal is restored on the way out
+        KEY_EOT    => return Some(Event::Quit),
+        KEY_ESCAPE => Key::Escape,
+        KEY_NEWLINE | KEY_ENTER => Key::Enter,
+        KEY_DELETE | KEY_BACKSPACE => Key::Backspace,
+        KEY_UP     => Key::Up,
+        KEY_DOWN   => Key::Down,
+        KEY_LEFT   => Key::Left,
+        KEY_RIGHT  => Key::Right,
+        KEY_SR     => Key::ShiftUp,
+        KEY_SF     => Key::ShiftDown,
+        KEY_SLEFT  => Key::ShiftLeft,
+        KEY_SRIGHT => Key::ShiftRight,
+        KEY_PPAGE  => Key::PageUp,
+        KEY_NPAGE  => Key::PageDown,
+        KEY_HOME   => Key::Home,
+        KEY_END    => Key::End,
+        character @ 0x20..=0x7e => Key::Char(character as u8 as char),
+        _ => return None,
+    };
+
+    Some(Event::Key(key))
+}
+
+fn decode_mouse() -> Option<Mouse> {
+    let mut mouse_event = ncurses::MEVENT { id: 0, x: 0, y: 0, z: 0, bstate: 0 };
+    ncurses::getmouse(&mut mouse_event as *mut ncurses::MEVENT);
+
+    let has = |mask: i32| (mouse_event.bstate & mask as ncurses::mmask_t) != 0;
+
+    let kind = if has(ncurses::REPORT_MOUSE_POSITION) {
+        MouseKind::Move
+    } else if has(ncurses::BUTTON1_PRESSED) {
+        MouseKind::Press(Button::Left)
+    } else if has(ncurses::BUTTON1_RELEASED) {
+        MouseKind::Release(Button::Left)
+    } else if has(ncurses::BUTTON3_PRESSED) {
+        MouseKind::Press(Button::Right)
+    } else if has(ncurses::BUTTON3_RELEASED) {
+        MouseKind::Release(Button::Right)
+    } else if has(ncurses::BUTTON4_PRESSED) {
+        MouseKind::WheelUp
+    } else if has(ncurses::BUTTON5_PRESSED) {
+        MouseKind::WheelDown
+    } else {
+        return None;
+    };
+
+    Some(Mouse {
+        at: Coord(mouse_event.x as usize, mouse_event.y as usize),
+        kind,
+        shift: has(ncurses::BUTTON_SHIFT),
+    })
+}
+
+fn pair(paint: Paint) -> i16 {
+    match paint {
+        Paint::Checker(parity) => CHECKER_1 + parity as i16,
+        Paint::Overlay => OVERLAY_1,
+        Paint::Points  => POINTS,
+        Paint::Penalty => PENALTY,
+        Paint::Digit(digit) => DIGITS + digit as i16,
+        Paint::Player(slot) => PLAYERS + slot as i16,
+    }
+}
+
+// Stand-ins for color pairs when colors are unavailable or unwanted
+fn mono_attributes(paint: Paint) -> ncurses::attr_t {
+    match paint {
+        Paint::Overlay | Paint::Player(_) => ncurses::A_REVERSE(),
+        Paint::Penalty | Paint::Points => ncurses::A_BOLD(),
+        _ => ncurses::A_NORMAL(),
+    }
+}
+
+fn color_depth() -> theme::Depth {
+    if ncurses::COLORS() < 256 {
+        theme::Depth::Basic
+    } else if ncurses::can_change_color() {
+        theme::Depth::Rgb
+    } else {
+        theme::Depth::Indexed
+    }
+}
+
+fn set_motion_tracking(sequence: &str) {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(sequence.as_bytes());
+    let _ = stdout.flush();
+}
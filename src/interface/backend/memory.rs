@@ -0,0 +1,145 @@
+use super::{Renderer, InputSource, Event, Style, buffer::{Buffer, Cell, BLANK}};
+use crate::{
+    aux::coord::Coord,
+    interface::theme::Theme,
+};
+
+use std::{collections::VecDeque, time::Duration};
+
+// A terminal with scrolling regions, drawn to through the same buffer as the
+// real ones. Only what's been refreshed shows up.
+pub struct MemoryScreen {
+    size: Coord<usize>,
+    buffer: Buffer,
+    pub mono: bool,
+    pub out_of_bounds: usize,
+    pub refreshes: usize,
+    // Cells sent to the terminal over all refreshes
+    pub sent: usize,
+}
+
+impl MemoryScreen {
+    pub fn new(size: Coord<usize>) -> Self {
+        let mut buffer = Buffer::default();
+        buffer.fit(size);
+
+        MemoryScreen {
+            size,
+            buffer,
+            mono: false,
+            out_of_bounds: 0,
+            refreshes: 0,
+            sent: 0,
+        }
+    }
+
+    pub fn cell(&self, Coord(x, y): Coord<usize>) -> Cell {
+        self.buffer.shown(Coord(x, y)).unwrap_or(BLANK)
+    }
+
+    pub fn row(&self, y: usize) -> String {
+        (0..self.size.0).map(|x| self.cell(Coord(x, y)).ch).collect()
+    }
+
+    pub fn text(&self) -> String {
+        (0..self.size.1).map(|y| self.row(y) + "\n").collect()
+    }
+}
+
+impl Renderer for MemoryScreen {
+    fn size(&self) -> Coord<usize> { self.size }
+    fn monochrome(&self) -> bool { self.mono }
+    fn set_theme(&mut self, _: &Theme) {}
+
+    fn put_str(&mut self, Coord(x, y): Coord<usize>, text: &str, style: Style) {
+        let width = text.chars().count();
+
+        if y >= self.size.1 || x >= self.size.0 {
+            self.out_of_bounds += 1;
+            return;
+        }
+
+        if width > self.size.0 - x {
+            self.out_of_bounds += 1;
+        }
+
+        self.buffer.put_str(Coord(x, y), text, style);
+    }
+
+    fn shift(&mut self, delta: Coord<isize>, rows: usize) {
+        self.buffer.shift(delta, rows);
+        if let Some(dy) = self.buffer.scrolling(delta, rows) {
+            self.buffer.scrolled(dy, rows);
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.refreshes += 1;
+
+        for run in self.buffer.changes() {
+            self.sent += run.text.chars().count();
+        }
+    }
+}
+
+// Plays back a fixed list of events, then quits
+pub struct ScriptedInput {
+    events: VecDeque<Event>,
+}
+
+impl ScriptedInput {
+    pub fn new(events: impl IntoIterator<Item=Event>) -> Self {
+        ScriptedInput { events: events.into_iter().collect() }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_event(&mut self) -> Event {
+        self.events.pop_front().unwrap_or(Event::Quit)
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    // Ticks are scripted like everything else
+    fn set_tick(&mut self, _tick: Duration) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::backend::Paint;
+
+    #[test]
+    fn clipping() {
+        let mut screen = MemoryScreen::new(Coord(4, 2));
+        let style = Style::from(Paint::Overlay);
+
+        screen.put_str(Coord(0, 0), "ab", style);
+        screen.put_str(Coord(3, 1), "cd", style);
+        screen.put_str(Coord(0, 2), "ef", style);
+        screen.refresh();
+
+        assert_eq!(screen.text(), "ab  \n   c\n");
+        assert_eq!(screen.out_of_bounds, 2);
+    }
+
+    #[test]
+    fn shifting() {
+        let mut screen = MemoryScreen::new(Coord(3, 3));
+        let style = Style::from(Paint::Overlay);
+        for (y, row) in ["abc", "def", "ghi"].iter().enumerate() {
+            screen.put_str(Coord(0, y), row, style);
+        }
+        screen.refresh();
+
+        screen.shift(Coord(1, 1), 2);
+        screen.refresh();
+        assert_eq!(screen.text(), "efc\ndef\nghi\n");
+
+        screen.shift(Coord(-1, 0), 3);
+        screen.refresh();
+        assert_eq!(screen.text(), "eef\ndde\nggh\n");
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(not(feature = "crossterm"))]
+pub mod curses;
+#[cfg(feature = "crossterm")]
+pub mod terminal;
+pub mod buffer;
+#[cfg(test)]
+pub mod memory;
+
+#[cfg(not(feature = "crossterm"))]
+pub use self::curses::init;
+#[cfg(feature = "crossterm")]
+pub use self::terminal::init;
+
+#[cfg(not(any(feature = "ncurses", feature = "crossterm")))]
+compile_error!("enable either the `ncurses` or the `crossterm` feature");
+
+use super::theme::Theme;
+use crate::aux::coord::Coord;
+
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Paint {
+    Checker(usize),
+    Overlay,
+    Points,
+    Penalty,
+    Digit(u8),
+    // Another player's cursor, by color slot
+    Player(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Style {
+    pub paint: Paint,
+    pub underline: bool,
+}
+
+impl From<Paint> for Style {
+    fn from(paint: Paint) -> Self { Style { paint, underline: false } }
+}
+
+pub trait Renderer {
+    fn size(&self) -> Coord<usize>;
+    fn monochrome(&self) -> bool;
+    fn set_theme(&mut self, theme: &Theme);
+    fn put_str(&mut self, at: Coord<usize>, text: &str, style: Style);
+    // Moves the top `rows` rows so content at `delta` lands on the origin
+    fn shift(&mut self, delta: Coord<isize>, rows: usize);
+    fn refresh(&mut self);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    ShiftUp,
+    ShiftDown,
+    ShiftLeft,
+    ShiftRight,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MouseKind {
+    Press(Button),
+    Release(Button),
+    Move,
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Mouse {
+    pub at: Coord<usize>,
+    pub kind: MouseKind,
+    pub shift: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Key(Key),
+    Mouse(Mouse),
+    Resize,
+    // Nothing happened for a while; lets the clock redraw
+    Tick,
+    Quit,
+}
+
+pub trait InputSource {
+    fn next_event(&mut self) -> Event;
+    // Only returns input that is already waiting
+    fn poll_event(&mut self) -> Option<Event>;
+    // How long `next_event` waits before giving up with a `Tick`
+    fn set_tick(&mut self, tick: Duration);
+}
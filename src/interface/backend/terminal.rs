@@ -0,0 +1,305 @@
+use super::{Renderer, InputSource, Event, Key, Mouse, MouseKind, Button, Paint, Style, buffer::Buffer};
+use crate::{
+    aux::coord::Coord,
+    interface::theme::{self, Theme},
+};
+
+use std::{
+    env,
+    io::{self, Stdout, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
+    style::{self, Attribute, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal,
+    queue,
+};
+
+const CLOCK_TICK_MS: u64 = 1000;
+
+// Keeps a copy of the screen so shifts and redraws only send changed cells
+pub struct Screen {
+    out: Stdout,
+    mono: bool,
+    depth: theme::Depth,
+    theme: Theme,
+    buffer: Buffer,
+}
+
+pub struct Input {
+    tick: Duration,
+}
+
+pub fn init(mono: bool) -> (Screen, Input) {
+    let mut out = io::stdout();
+
+    let _ = terminal::enable_raw_mode();
+    let _ = queue!(
+        out,
+        terminal::EnterAlternateScreen,
+        event::EnableMouseCapture,
+        cursor::Hide,
+        terminal::Clear(terminal::ClearType::All),
+    );
+    let _ = out.flush();
+
+    let mut screen = Screen {
+        out,
+        mono,
+        depth: color_depth(),
+        theme: Theme::default(),
+        buffer: Buffer::default(),
+    };
+    screen.fit();
+
+    (screen, Input { tick: Duration::from_millis(CLOCK_TICK_MS) })
+}
+
+impl Screen {
+    // Matches the buffer to the terminal, which may have been resized
+    fn fit(&mut self) {
+        let size = self.size();
+        if self.buffer.fit(size) {
+            let _ = queue!(self.out, SetAttribute(Attribute::Reset), terminal::Clear(terminal::ClearType::All));
+        }
+    }
+
+    fn set_style(&mut self, style: Option<Style>) {
+        let _ = queue!(self.out, SetAttribute(Attribute::Reset));
+        let style = match style {
+            Some(style) => style,
+            None => return,
+        };
+
+        if style.underline {
+            let _ = queue!(self.out, SetAttribute(Attribute::Underlined));
+        }
+
+        if self.mono {
+            let attribute = match style.paint {
+                Paint::Overlay | Paint::Player(_) => Attribute::Reverse,
+                Paint::Penalty | Paint::Points => Attribute::Bold,
+                _ => return,
+            };
+            let _ = queue!(self.out, SetAttribute(attribute));
+            return;
+        }
+
+        let theme::Pair { fg, bg } = match style.paint {
+            Paint::Checker(parity) => self.theme.checker[parity],
+            Paint::Overlay => self.theme.overlay,
+            Paint::Points  => self.theme.points,
+            Paint::Penalty => self.theme.penalty,
+            Paint::Digit(digit) => theme::Pair {
+                fg: self.theme.digits[digit as usize],
+                bg: self.theme.clicked.bg,
+            },
+            Paint::Player(slot) => self.theme.players[slot as usize],
+        };
+        let (fg, bg) = (self.color(fg), self.color(bg));
+        let _ = queue!(self.out, SetForegroundColor(fg), SetBackgroundColor(bg));
+    }
+
+    fn color(&self, color: theme::Color) -> style::Color {
+        match (color, self.depth) {
+            (theme::Color::Rgb(r, g, b), theme::Depth::Rgb) => style::Color::Rgb { r, g, b },
+            // Nothing gets redefined here, so the terminal's own slots will do
+            (theme::Color::Indexed(index), theme::Depth::Rgb) => style::Color::AnsiValue(index),
+            _ => style::Color::AnsiValue(theme::Palette::new(self.depth).index(color) as u8),
+        }
+    }
+}
+
+impl Renderer for Screen {
+    fn size(&self) -> Coord<usize> {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        Coord(width as usize, height as usize)
+    }
+
+    fn monochrome(&self) -> bool { self.mono }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        if self.mono { return; }
+
+        self.theme = theme.clone();
+        self.buffer.invalidate();
+    }
+
+    fn put_str(&mut self, at: Coord<usize>, text: &str, style: Style) {
+        self.fit();
+        self.buffer.put_str(at, text, style);
+    }
+
+    fn shift(&mut self, delta: Coord<isize>, rows: usize) {
+        self.fit();
+        self.buffer.shift(delta, rows);
+
+        // Vertical shifts are left to the terminal, scrolling just those
+        // rows so only the ones scrolled in need sending
+        let dy = match self.buffer.scrolling(delta, rows) {
+            Some(dy) => dy,
+            None => return,
+        };
+        let rows = rows.min(self.buffer.size().1);
+
+        let _ = queue!(self.out, SetAttribute(Attribute::Reset), Print(format!("\x1b[1;{}r", rows)));
+        let _ = if dy > 0 {
+            queue!(self.out, terminal::ScrollUp(dy as u16))
+        } else {
+            queue!(self.out, terminal::ScrollDown(-dy as u16))
+        };
+        let _ = queue!(self.out, Print("\x1b[r"));
+        self.buffer.scrolled(dy, rows);
+    }
+
+    fn refresh(&mut self) {
+        self.fit();
+
+        let mut style = None;
+        let mut cursor = None;
+
+        for run in self.buffer.changes() {
+            if cursor != Some(run.at) {
+                let Coord(x, y) = run.at;
+                let _ = queue!(self.out, cursor::MoveTo(x as u16, y as u16));
+            }
+            if style != Some(run.style) {
+                self.set_style(run.style);
+                style = Some(run.style);
+            }
+
+            let _ = queue!(self.out, Print(&run.text));
+            cursor = Some(run.at + Coord(run.text.chars().count(), 0));
+        }
+
+        let _ = self.out.flush();
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = queue!(
+            self.out,
+            SetAttribute(Attribute::Reset),
+            cursor::Show,
+            event::DisableMouseCapture,
+            terminal::LeaveAlternateScreen,
+        );
+        let _ = self.out.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl InputSource for Input {
+    fn next_event(&mut self) -> Event {
+        loop {
+            match event::poll(self.tick) {
+                Ok(true) => (),
+                Ok(false) => return Event::Tick,
+                Err(_) => return Event::Quit,
+            }
+
+            match event::read() {
+                Ok(input) => if let Some(event) = decode(input) { return event; },
+                Err(_) => return Event::Quit,
+            }
+        }
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        while let Ok(true) = event::poll(Duration::default()) {
+            if let Some(event) = event::read().ok().and_then(decode) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    fn set_tick(&mut self, tick: Duration) {
+        self.tick = tick;
+    }
+}
+
+fn decode(input: event::Event) -> Option<Event> {
+    match input {
+        event::Event::Key(key) => decode_key(key),
+        event::Event::Mouse(mouse) => decode_mouse(mouse).map(Event::Mouse),
+        event::Event::Resize(..) => Some(Event::Resize),
+        _ => None,
+    }
+}
+
+fn decode_key(key: event::KeyEvent) -> Option<Event> {
+    if key.kind == KeyEventKind::Release { return None; }
+
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    let key = match key.code {
+        // Raw mode swallows the interrupt signal, so quit by hand
+        KeyCode::Char('c') | KeyCode::Char('d') if control => return Some(Event::Quit),
+        KeyCode::Char(_) if control => return None,
+        KeyCode::Char(character) if character.is_ascii() && !character.is_ascii_control() => Key::Char(character),
+        KeyCode::Esc       => Key::Escape,
+        KeyCode::Enter     => Key::Enter,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Up    if shift => Key::ShiftUp,
+        KeyCode::Down  if shift => Key::ShiftDown,
+        KeyCode::Left  if shift => Key::ShiftLeft,
+        KeyCode::Right if shift => Key::ShiftRight,
+        KeyCode::Up       => Key::Up,
+        KeyCode::Down     => Key::Down,
+        KeyCode::Left     => Key::Left,
+        KeyCode::Right    => Key::Right,
+        KeyCode::PageUp   => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Home     => Key::Home,
+        KeyCode::End      => Key::End,
+        _ => return None,
+    };
+
+    Some(Event::Key(key))
+}
+
+fn decode_mouse(mouse: event::MouseEvent) -> Option<Mouse> {
+    let button = |button| match button {
+        MouseButton::Left  => Some(Button::Left),
+        MouseButton::Right => Some(Button::Right),
+        MouseButton::Middle => None,
+    };
+
+    let kind = match mouse.kind {
+        MouseEventKind::Down(pressed) => MouseKind::Press(button(pressed)?),
+        MouseEventKind::Up(released)  => MouseKind::Release(button(released)?),
+        MouseEventKind::Drag(_) | MouseEventKind::Moved => MouseKind::Move,
+        MouseEventKind::ScrollUp   => MouseKind::WheelUp,
+        MouseEventKind::ScrollDown => MouseKind::WheelDown,
+        _ => return None,
+    };
+
+    Some(Mouse {
+        at: Coord(mouse.column as usize, mouse.row as usize),
+        kind,
+        shift: mouse.modifiers.contains(KeyModifiers::SHIFT),
+    })
+}
+
+fn color_depth() -> theme::Depth {
+    let truecolor = env::var("COLORTERM")
+        .map(|value| value == "truecolor" || value == "24bit")
+        .unwrap_or(false);
+    let indexed = env::var("TERM")
+        .map(|value| value.contains("256color"))
+        .unwrap_or(false);
+
+    if truecolor {
+        theme::Depth::Rgb
+    } else if indexed {
+        theme::Depth::Indexed
+    } else {
+        theme::Depth::Basic
+    }
+}
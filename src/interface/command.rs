@@ -0,0 +1,131 @@
+use super::status::Field;
+use crate::aux::coord::Coord;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Goto(Coord<isize>),
+    GotoChunk(Coord<isize>),
+    Mark(String),
+    Unmark(String),
+    Jump(String),
+    Spawn,
+    Unfinished,
+    Explosion,
+    Status(StatusSetting),
+    Theme(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StatusSetting {
+    Show,
+    Hide,
+    Fields(Vec<Field>),
+}
+
+pub fn parse(input: &str) -> Result<Command, &'static str> {
+    let mut words = input.split_whitespace();
+    let first = words.next().ok_or("Empty command")?;
+    let rest = words.collect::<Vec<_>>().join(" ");
+
+    match first {
+        "spawn"      => no_args(&rest, Command::Spawn),
+        "unfinished" => no_args(&rest, Command::Unfinished),
+        "boom"       => no_args(&rest, Command::Explosion),
+        "c" | "chunk" => parse_coord(&rest).map(Command::GotoChunk),
+        "mark"   => parse_name(&rest).map(Command::Mark),
+        "unmark" => parse_name(&rest).map(Command::Unmark),
+        "go"     => parse_name(&rest).map(Command::Jump),
+        "status" => parse_status(&rest).map(Command::Status),
+        "theme"  => if rest.is_empty() { Err("Missing theme name or path") } else { Ok(Command::Theme(rest)) },
+        _ => parse_coord(input).map(Command::Goto),
+    }
+}
+
+fn no_args(rest: &str, command: Command) -> Result<Command, &'static str> {
+    if rest.is_empty() { Ok(command) } else { Err("Unexpected arguments") }
+}
+
+fn parse_name(rest: &str) -> Result<String, &'static str> {
+    if rest.is_empty() { Err("Missing bookmark name") } else { Ok(rest.to_owned()) }
+}
+
+fn parse_status(rest: &str) -> Result<StatusSetting, &'static str> {
+    match rest {
+        "on"  => Ok(StatusSetting::Show),
+        "off" => Ok(StatusSetting::Hide),
+        ""    => Err("Expected on, off or field names"),
+        _ => rest
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .map(|word| Field::from_name(word).ok_or("Unknown status field"))
+            .collect::<Result<Vec<_>, _>>()
+            .map(StatusSetting::Fields),
+    }
+}
+
+fn parse_coord(text: &str) -> Result<Coord<isize>, &'static str> {
+    let numbers = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(str::parse::<isize>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Unknown command")?;
+
+    match numbers.as_slice() {
+        &[x, y] => Ok(Coord(x, y)),
+        _ => Err("Expected two coordinates"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(parse("12,-40"),   Ok(Command::Goto(Coord(12, -40))));
+        assert_eq!(parse(" 12  -40"), Ok(Command::Goto(Coord(12, -40))));
+        assert_eq!(parse("c 3, -5"),  Ok(Command::GotoChunk(Coord(3, -5))));
+        assert_eq!(parse("chunk 0 0"), Ok(Command::GotoChunk(Coord(0, 0))));
+        assert!(parse("12").is_err());
+        assert!(parse("1 2 3").is_err());
+        assert!(parse("c").is_err());
+    }
+
+    #[test]
+    fn bookmarks() {
+        assert_eq!(parse("mark home base"), Ok(Command::Mark("home base".into())));
+        assert_eq!(parse("go home base"),   Ok(Command::Jump("home base".into())));
+        assert_eq!(parse("unmark home"),    Ok(Command::Unmark("home".into())));
+        assert!(parse("mark").is_err());
+    }
+
+    #[test]
+    fn theme() {
+        assert_eq!(parse("theme dark"), Ok(Command::Theme("dark".into())));
+        assert_eq!(parse("theme my theme.txt"), Ok(Command::Theme("my theme.txt".into())));
+        assert!(parse("theme").is_err());
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(parse("status on"),  Ok(Command::Status(StatusSetting::Show)));
+        assert_eq!(parse("status off"), Ok(Command::Status(StatusSetting::Hide)));
+        assert_eq!(
+            parse("status time, seed"),
+            Ok(Command::Status(StatusSetting::Fields(vec![Field::Time, Field::Seed]))),
+        );
+        assert!(parse("status time bogus").is_err());
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(parse("spawn"),      Ok(Command::Spawn));
+        assert_eq!(parse("unfinished"), Ok(Command::Unfinished));
+        assert_eq!(parse("boom"),       Ok(Command::Explosion));
+        assert!(parse("spawn now").is_err());
+        assert!(parse("status").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("bogus").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Charset {
+    #[default]
+    Ascii,
+    Unicode,
+}
+
+// Every glyph is two columns wide, matching one board square
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Glyphs {
+    pub unclicked: [&'static str; 2],
+    pub flagged: &'static str,
+    pub penalty: &'static str,
+    pub points: &'static str,
+    pub digits: [&'static str; 9],
+    pub chunk_border: Option<char>,
+}
+
+const DIGITS: [&str; 9] = ["  ", " 1", " 2", " 3", " 4", " 5", " 6", " 7", " 8"];
+
+// Relies on color to tell squares apart
+pub const CLASSIC: Glyphs = Glyphs {
+    unclicked: ["  ", "  "],
+    flagged: "/>",
+    penalty: "><",
+    points:  "<>",
+    digits: DIGITS,
+    chunk_border: None,
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    unclicked: ["[]", "[]"],
+    flagged: "|>",
+    penalty: "**",
+    points:  "$$",
+    digits: [" .", " 1", " 2", " 3", " 4", " 5", " 6", " 7", " 8"],
+    chunk_border: None,
+};
+
+pub const UNICODE: Glyphs = Glyphs {
+    unclicked: ["  ", "  "],
+    flagged: " ⚑",
+    penalty: " ✸",
+    points:  " ◆",
+    digits: DIGITS,
+    chunk_border: Some('│'),
+};
+
+pub const UNICODE_MONO: Glyphs = Glyphs {
+    unclicked: ["░░", "▒▒"],
+    flagged: " ⚑",
+    penalty: " ✸",
+    points:  " ◆",
+    digits: [" ·", " 1", " 2", " 3", " 4", " 5", " 6", " 7", " 8"],
+    chunk_border: Some('│'),
+};
+
+impl Default for Glyphs {
+    fn default() -> Self { CLASSIC }
+}
+
+pub fn select(charset: Charset, mono: bool) -> Glyphs {
+    match (charset, mono) {
+        (Charset::Ascii,   false) => CLASSIC,
+        (Charset::Ascii,   true)  => ASCII,
+        (Charset::Unicode, false) => UNICODE,
+        (Charset::Unicode, true)  => UNICODE_MONO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_glyphs(glyphs: &Glyphs) -> Vec<&'static str> {
+        let mut all = vec![glyphs.flagged, glyphs.penalty, glyphs.points];
+        all.extend(glyphs.unclicked.iter());
+        all.extend(glyphs.digits.iter());
+        all
+    }
+
+    #[test]
+    fn two_columns() {
+        for glyphs in [CLASSIC, ASCII, UNICODE, UNICODE_MONO].iter() {
+            for glyph in all_glyphs(glyphs) {
+                assert_eq!(glyph.chars().count(), 2, "{:?}", glyph);
+            }
+        }
+    }
+
+    #[test]
+    fn mono_glyphs_distinct() {
+        for glyphs in [ASCII, UNICODE_MONO].iter() {
+            let mut all = all_glyphs(glyphs);
+            all.remove(4); // Both checker squares are unclicked
+            let count = all.len();
+            all.sort();
+            all.dedup();
+            assert_eq!(all.len(), count);
+        }
+    }
+
+    #[test]
+    fn ascii_only() {
+        for glyph in all_glyphs(&ASCII).into_iter().chain(all_glyphs(&CLASSIC)) {
+            assert!(glyph.is_ascii());
+        }
+    }
+}
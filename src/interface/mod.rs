@@ -1,246 +1,963 @@
+pub mod backend;
+pub mod theme;
+pub mod glyphs;
+mod command;
+mod status;
+#[cfg(test)]
+mod snapshots;
+
+use self::{
+    backend::{Renderer, InputSource, Event, Key, Mouse, MouseKind, Button, Paint, Style},
+    command::{Command, StatusSetting},
+    glyphs::{Charset, Glyphs},
+    status::Stats,
+    theme::{Theme, PLAYER_COLORS},
+};
 use crate::{
     aux::{
         coord::Coord,
-        index_iter::{IndexIterSigned, IndexIterUnsigned},
+        index_iter::IndexIterSigned,
         ModuloSignedExt,
         DivFloorSignedExt,
     },
-    game::{self, Game, AbsoluteCoord},
+    game::{self, Game, AbsoluteCoord, event::Event as Change},
+    net::{client::Client, protocol::{Mode, PlayerId}},
 };
 
 use std::{
     ops::{Add, Rem},
+    sync::mpsc::Receiver,
     thread,
-    time::Duration,
-    mem,
+    time::{Duration, Instant},
 };
 
-use ncurses::{self, COLOR_PAIR};
-
-const CHECKER_1: i16 = 10;
-const CHECKER_2: i16 = 11;
-const OVERLAY_1: i16 = 20;
-const OVERLAY_2: i16 = 21;
-const POINTS:    i16 =  8;
-const PENALTY:   i16 =  9;
-
 const SPREAD_DELAY_MS: u64 = 30;
+// How often to check for news from the server when playing online
+const REMOTE_TICK_MS: u64 = 50;
+const WHEEL_STEP: isize = 3;
+const CHUNK: Coord<isize> = Coord::squared(game::chunk::DIMENSION as isize);
+// Chunks generated in the background past each edge of the view
+const PREGENERATE_CHUNKS: isize = 2;
 
-#[derive(Default)]
-pub struct Interface {
+const PROMPT_PREFIX: &str = ":";
+
+pub struct Interface<R: Renderer, I: InputSource> {
+    renderer: R,
+    input: I,
+    pending: Option<Event>,
     scroll: Coord<isize>,
     size: Coord<usize>,
     spread_delay: Duration,
+    message: Option<String>,
+    drag: Option<Drag>,
+    mouse: Option<Coord<usize>>,
+    started: Option<Instant>,
+    status_fields: Vec<status::Field>,
+    status_hidden: bool,
+    glyphs: Glyphs,
+    // What the game changed since the last redraw
+    changes: Option<Receiver<Change>>,
+    // The server actually playing the game, if it's online
+    remote: Option<Client>,
+    // The player a spectator's view keeps up with
+    follow: Option<PlayerId>,
+}
+
+enum Redraw {
+    Scroll(Coord<isize>),
+    Overlay,
+    Chunks,
 }
 
-impl Interface {
-    pub fn new() -> Interface {
-        use ncurses::*;
-        
-        let window = initscr();
-        
-        cbreak();
-        keypad(window, true);
-        mousemask(ALL_MOUSE_EVENTS as mmask_t, None);
-        mouseinterval(0);
-        noecho();
-        curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-        
-        start_color();
-        for i in 0..POINTS { init_pair(i, COLOR_WHITE, COLOR_BLACK); }
-        init_pair(POINTS,  COLOR_BLACK, COLOR_YELLOW);
-        init_pair(PENALTY, COLOR_YELLOW,  COLOR_RED);
-        init_pair(OVERLAY_1, COLOR_WHITE, COLOR_BLACK);
-        init_pair(OVERLAY_2, COLOR_GREEN, COLOR_BLACK);
-        init_pair(CHECKER_1, COLOR_BLACK, COLOR_WHITE);
-        init_pair(CHECKER_2, COLOR_BLACK, COLOR_GREEN);
-        
-        let mut ret = Interface::default();
-        ret.spread_delay = Duration::from_millis(SPREAD_DELAY_MS);
+struct Drag {
+    last: Coord<isize>,
+    moved: bool,
+}
+
+impl<R: Renderer, I: InputSource> Interface<R, I> {
+    pub fn new(renderer: R, input: I, charset: Charset) -> Self {
+        let mut ret = Interface {
+            glyphs: glyphs::select(charset, renderer.monochrome()),
+            renderer,
+            input,
+            pending: None,
+            scroll: Coord::default(),
+            size: Coord::default(),
+            spread_delay: Duration::from_millis(SPREAD_DELAY_MS),
+            message: None,
+            drag: None,
+            mouse: None,
+            started: None,
+            status_fields: status::DEFAULT_FIELDS.to_vec(),
+            status_hidden: false,
+            changes: None,
+            remote: None,
+            follow: None,
+        };
         ret.resize();
-        return ret;
+        ret
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.renderer.set_theme(theme);
     }
 
-    pub fn play(&mut self, mut game: Game) {
+    // Plays online through `client`, whose `game` should be passed to `play`.
+    // Spectators just watch, optionally following a player around.
+    pub fn connect(&mut self, client: Client) {
+        if client.watching() {
+            self.message = Some("Watching; f follows each player in turn".to_owned());
+        }
+
+        let field = match client.mode() {
+            Mode::Cooperative => status::Field::Players,
+            Mode::Race => status::Field::Scoreboard,
+        };
+        if !self.status_fields.contains(&field) {
+            self.status_fields.push(field);
+        }
+
+        self.remote = Some(client);
+        self.input.set_tick(Duration::from_millis(REMOTE_TICK_MS));
+    }
+
+    // Hands the game back once the player quits
+    pub fn play(&mut self, mut game: Game) -> Game {
+        self.started = Some(Instant::now());
+        self.changes = Some(game.subscribe());
+        self.page_in(&mut game);
         self.render_full(&game);
         
         loop {
-            match ncurses::getch() {
-                ncurses::KEY_MOUSE => {
-                    self.mouse_click_event(&mut game);
-                    self.render_partial(&game);
+            self.sync_remote(&mut game);
+
+            let event = match self.pending.take() {
+                Some(event) => event,
+                None => self.input.next_event(),
+            };
+
+            match event {
+                Event::Tick | Event::Mouse(_) => (),
+                _ => self.message = None,
+            }
+
+            match event {
+                Event::Quit => return game,
+                Event::Tick => {
+                    // Timed out waiting for input; keep the clock moving
+                    self.print_overlay(&game);
+                    self.renderer.refresh();
+                },
+                Event::Mouse(mouse) => match self.mouse_event(&mut game, mouse) {
+                    Redraw::Scroll(delta) => self.scroll_by(&mut game, delta),
+                    Redraw::Overlay => {
+                        self.print_overlay(&game);
+                        self.renderer.refresh();
+                    },
+                    Redraw::Chunks => {
+                        self.page_in(&mut game);
+                        self.render_partial(&game);
+                    },
                 },
-                ncurses::KEY_RESIZE => {
+                Event::Resize => {
                     self.resize();
+                    self.page_in(&mut game);
+                    self.render_full(&game);
+                },
+                Event::Key(Key::Char(':')) => {
+                    self.command_event(&mut game);
+                    self.page_in(&mut game);
                     self.render_full(&game);
                 },
-                character @ ncurses::KEY_DOWN..=ncurses::KEY_RIGHT => {
-                    self.arrow_key_event(character);
+                Event::Key(Key::Char('f')) if self.spectating() => {
+                    self.follow_next();
+                    self.page_in(&mut game);
                     self.render_full(&game);
                 },
-                _ => ()
+                Event::Key(key) => if let Some(delta) = self.scroll_key_delta(key) {
+                    let delta = self.coalesce_scroll_keys(delta);
+                    self.scroll_by(&mut game, delta);
+                },
             }
         }
     }
 
-    fn render_partial(&self, game: &Game) {
-        self.print_chunks(game);
+    // Draws whatever the server has changed since last time
+    fn sync_remote(&mut self, game: &mut Game) {
+        let client = match self.remote.as_mut() {
+            Some(client) => client,
+            None => return,
+        };
+
+        let was_connected = client.connected();
+        let changed = client.sync(game);
+        if was_connected && !client.connected() {
+            self.message = Some("Lost connection to the server".to_owned());
+        } else if changed.is_empty() {
+            return;
+        }
+
+        if self.follow_cursor() {
+            self.page_in(game);
+            self.render_full(game);
+            return;
+        }
+
+        for world_coord in changed {
+            self.print_world_area(game, world_coord, Coord::squared(1));
+        }
+        self.print_overlay(game);
+        self.renderer.refresh();
+    }
+
+    fn spectating(&self) -> bool {
+        self.remote.as_ref().is_some_and(Client::watching)
+    }
+
+    // Follows each player in the order they joined, then goes back to
+    // leaving the view alone
+    fn follow_next(&mut self) {
+        let client = match self.remote.as_ref() {
+            Some(client) => client,
+            None => return,
+        };
+
+        let current = self.follow;
+        self.follow = client.players()
+            .keys()
+            .cloned()
+            // Anyone's after nobody
+            .find(|&id| Some(id) > current);
+        self.message = Some(match self.follow {
+            Some(id) => format!("Following {}", client.players()[&id].name),
+            None => "Free camera".to_owned(),
+        });
+
+        self.follow_cursor();
+    }
+
+    // Brings the followed player's cursor back into view if it's left.
+    // Returns whether it had to.
+    fn follow_cursor(&mut self) -> bool {
+        let cursor = match (self.follow, self.remote.as_ref()) {
+            (Some(id), Some(client)) => client.players().get(&id).and_then(|player| player.cursor),
+            _ => None,
+        };
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => return false,
+        };
+
+        let view = Coord::<isize>::from(self.view_squares());
+        let Coord(x, y) = cursor - self.scroll;
+        if 0 <= x && x < view.0 && 0 <= y && y < view.1 { return false; }
+
+        self.center_on(cursor);
+        true
+    }
+
+    // Redraws only what the game says has changed
+    fn render_partial(&mut self, game: &Game) {
+        let changes: Vec<_> = match self.changes.as_ref() {
+            Some(changes) => changes.try_iter().collect(),
+            None => return self.render_full(game),
+        };
+        
+        for change in changes {
+            match change {
+                Change::SquareRevealed(world_coord) | Change::FlagToggled(world_coord) =>
+                    self.print_world_area(game, world_coord, Coord::squared(1)),
+                Change::ChunkWon(chunk) | Change::ChunkLost(chunk) =>
+                    self.print_world_area(game, chunk * CHUNK, CHUNK),
+                // New chunks look the same as the checkerboard, and the
+                // score is in the overlay which is always redrawn
+                Change::ChunkAllocated(_) | Change::ScoreChanged(_) => (),
+            }
+        }
+        
         self.print_overlay(game);
-        ncurses::refresh();
+        self.renderer.refresh();
     }
 
-    fn render_full(&self, game: &Game) {
+    fn render_full(&mut self, game: &Game) {
+        // Everything's about to be redrawn anyway
+        if let Some(changes) = self.changes.as_ref() { changes.try_iter().for_each(drop); }
+        
         self.print_checkerboard();
         self.print_chunks(game);
         self.print_overlay(game);
-        ncurses::refresh();
+        self.renderer.refresh();
+    }
+    
+    fn scroll_by(&mut self, game: &mut Game, delta: Coord<isize>) {
+        if delta == Coord::default() { return; }
+        self.scroll += delta;
+        self.page_in(game);
+        
+        let view = Coord::<isize>::from(self.view_squares());
+        let Coord(dx, dy) = delta;
+        
+        if dx.abs() >= view.0 || dy.abs() >= view.1 {
+            self.render_full(game);
+            return;
+        }
+        
+        // Repaint only the strips uncovered by the shift
+        self.renderer.shift(delta * Coord(2, 1), view.1 as usize);
+        
+        let columns = if dx > 0 { view.0 - dx } else { 0 };
+        let rows    = if dy > 0 { view.1 - dy } else { 0 };
+        self.print_area(game, Coord(columns, 0), Coord(dx.abs(), view.1));
+        self.print_area(game, Coord(0, rows), Coord(view.0, dy.abs()));
+        
+        self.print_overlay(game);
+        self.renderer.refresh();
+    }
+    
+    fn view_squares(&self) -> Coord<usize> {
+        Coord(self.size.0 / 2, self.size.1.saturating_sub(self.overlay_rows()))
+    }
+    
+    fn overlay_rows(&self) -> usize {
+        if self.status_hidden { 0 } else { 1 }
     }
     
     fn resize(&mut self) {
-        self.size = Coord(
-            // Safe because extern statics are not being modified
-            unsafe { ncurses::COLS  } as usize,
-            unsafe { ncurses::LINES } as usize,
-        );
+        self.size = self.renderer.size();
     }
     
-    fn checker_color(&self, square: Coord<usize>) -> i16 {
+    fn checker_color(&self, square: Coord<usize>) -> Paint {
         let modulo = self
             .scroll
             .add(square.into())
             .rem(Coord::squared(2))
             .sum()
-            .modulo(2) as i16;
-        
-        CHECKER_1 + modulo
-    }
-
-    fn visible_chunks<'a>(&self, game: &'a Game) -> impl Iterator<Item=(Coord<isize>, &'a game::chunk::Chunk)> {
-        let far_corner = self.scroll + Coord::from(self.size/Coord(2,1));
-
-        let min = self.scroll.map(|x| x.div_floor(8)    );
-        let max = far_corner .map(|x| x.div_floor(8) + 1);
-        let dimension = (max - min).abs();
-
-        IndexIterSigned::new(dimension, min).filter_map(move |coord|
-            game.get_chunk(coord).map(|chunk| (coord, chunk))
-        )
-    }
-    
-    fn print_checkerboard(&self) {
-        let checker_size = self.size / Coord(2,1);
-        IndexIterUnsigned::new(checker_size, Coord::default())
-            .map(|square| (square, self.checker_color(square)))
-            .map(|(square, color)| (square * Coord(2,1), color))
-            .for_each(|(Coord(x, y), color)|
-                with_color(
-                    color,
-                    || { ncurses::mvaddstr(y as i32, x as i32, "  "); }
-                )
-            );
-    }
-    
-    fn print_chunks(&self, game: &Game) {
-        for (chunk, chunk_ref) in self.visible_chunks(game) {
-            for square in game::chunk::all_squares() {
-                let world_space = Coord::from(AbsoluteCoord { chunk, square });
-                let screen_space = self.world_to_screen_space(world_space);
-
-                let color = self.checker_color(screen_space / Coord(2, 1));
-                
-                let Coord(x, y) = screen_space.map(|x| x as i32);
-                
-                use self::game::SquareView::*;
-                match chunk_ref.view(square) {
-                    Unclicked  => with_color(color,     || { ncurses::mvaddstr(y, x, "  "); }),
-                    Flagged    => with_color(color,     || { ncurses::mvaddstr(y, x, "/>"); }),
-                    Penalty    => with_color(PENALTY,   || { ncurses::mvaddstr(y, x, "><"); }),
-                    Points     => with_color(POINTS,    || { ncurses::mvaddstr(y, x, "<>"); }),
-                    Clicked(n) => with_color(OVERLAY_1, || {
-                        ncurses::mvaddch(y, x, ' ' as u64);
-                        ncurses::mvaddch(
-                            y, x+1,
-                            if n == 0 { b' ' } else { (n + b'0') } as u64
-                        );
-                    }),
-                }
+            .modulo(2) as usize;
+        
+        Paint::Checker(modulo)
+    }
+
+    // Chunks with at least one square on screen, as a half-open range
+    fn visible_chunk_range(&self) -> (Coord<isize>, Coord<isize>) {
+        let far_corner = self.scroll + Coord::from(self.view_squares()) - Coord::squared(1);
+
+        let min = self.scroll.map(|x| DivFloorSignedExt::div_floor(x, 8)    );
+        let max = far_corner .map(|x| DivFloorSignedExt::div_floor(x, 8) + 1);
+        (min, max)
+    }
+
+    // Makes sure paged-out chunks in view are back in memory, or online,
+    // that the server is sending them
+    fn page_in(&mut self, game: &mut Game) {
+        const AHEAD: Coord<isize> = Coord::squared(PREGENERATE_CHUNKS);
+
+        let (min, max) = self.visible_chunk_range();
+        if let Some(client) = self.remote.as_mut() {
+            client.view(min, max);
+            return;
+        }
+        game.page_in(min, max);
+        game.pregenerate(min - AHEAD, max + AHEAD);
+        
+        // Also catches moves turned down since the last time
+        if let Some(error) = game.take_store_error() {
+            self.message = Some(format!("Cannot read paged-out chunks: {}", error));
+        }
+    }
+
+    fn print_checkerboard(&mut self) {
+        let checker_size = Coord::<isize>::from(self.size / Coord(2,1));
+        for square in IndexIterSigned::new(checker_size, Coord::default()) {
+            self.print_square(Coord::from(square * Coord(2,1)), game::SquareView::Unclicked);
+        }
+    }
+    
+    // Chunks at the edges are only partly on screen, so they're clipped
+    fn print_chunks(&mut self, game: &Game) {
+        let (min, max) = self.visible_chunk_range();
+        
+        for chunk in IndexIterSigned::new(max - min, min) {
+            if game.get_chunk(chunk).is_some() {
+                self.print_world_area(game, chunk * CHUNK, CHUNK);
+            }
+        }
+    }
+    
+    fn print_area(&mut self, game: &Game, min: Coord<isize>, dimension: Coord<isize>) {
+        for screen_square in IndexIterSigned::new(dimension, min) {
+            let world_space = self.scroll + screen_square;
+            let AbsoluteCoord { chunk, square } = world_space.into();
+            let screen_space = self.world_to_screen_space(world_space);
+            
+            match game.get_chunk(chunk) {
+                Some(chunk_ref) => self.print_square(screen_space, chunk_ref.view(square)),
+                None => self.print_square(screen_space, game::SquareView::Unclicked),
             }
+        }
+    }
+    
+    // Like `print_area`, but in world space and clipped to the view
+    fn print_world_area(&mut self, game: &Game, min: Coord<isize>, dimension: Coord<isize>) {
+        let view = Coord::<isize>::from(self.view_squares());
+        let Coord(left,  top)    = min - self.scroll;
+        let Coord(right, bottom) = min + dimension - self.scroll;
+        
+        let min = Coord(left.max(0), top.max(0));
+        let max = Coord(right.min(view.0), bottom.min(view.1));
+        
+        if min.0 < max.0 && min.1 < max.1 {
+            self.print_area(game, min, max - min);
+        }
+    }
+    
+    fn print_square(&mut self, screen_space: Coord<usize>, view: game::SquareView) {
+        let color = self.checker_color(screen_space / Coord(2, 1));
+        let parity = match color { Paint::Checker(parity) => parity, _ => 0 };
+        
+        use self::game::SquareView::*;
+        let (paint, glyph) = match view {
+            Unclicked  => (color,          self.glyphs.unclicked[parity]),
+            Flagged    => (color,          self.glyphs.flagged),
+            Penalty    => (Paint::Penalty, self.glyphs.penalty),
+            Points     => (Paint::Points,  self.glyphs.points),
+            Clicked(n) => (Paint::Digit(n), self.glyphs.digits[n as usize]),
+        };
+        
+        let world_coord = self.screen_to_world_space(screen_space);
+        let AbsoluteCoord { square, .. } = world_coord.into();
+
+        // Other players' cursors show through whatever they're over
+        let paint = match self.remote.as_ref().and_then(|client| client.cursor_at(world_coord)) {
+            Some(id) => Paint::Player(((id as usize - 1) % PLAYER_COLORS) as u8),
+            None => paint,
         };
+        let mut style = Style::from(paint);
+        let mut text = glyph.to_owned();
+        
+        if let Some(border) = self.glyphs.chunk_border {
+            if square.0 == 0 {
+                text = std::iter::once(border).chain(glyph.chars().skip(1)).collect();
+            }
+            style.underline = square.1 == game::chunk::DIMENSION - 1;
+        }
+        
+        self.renderer.put_str(screen_space, &text, style);
     }
 
-    fn print_overlay(&self, game: &Game) {
-        let message = format!(
-            "Solved: {} | Exploded: {} | Allocated: {}",
-            game.chunks_won(),
-            game.chunks_lost(),
-            game.chunks.len(),
-        );
+    fn print_overlay(&mut self, game: &Game) {
+        if let Some(message) = self.message.clone() {
+            self.print_status_line(2, &message);
+            return;
+        }
+        
+        if self.status_hidden {
+            // Repaint the world underneath any leftover prompt or message
+            let Coord(width, height) = Coord::<isize>::from(self.view_squares());
+            self.print_area(game, Coord(0, height - 1), Coord(width, 1));
+            return;
+        }
+        
+        let stats = Stats {
+            game,
+            position: self.mouse.map(|mouse| self.screen_to_world_space(mouse)),
+            elapsed: self.started.map(|started| started.elapsed()).unwrap_or_default(),
+            players: self.remote.as_ref().map(Client::players),
+        };
+        let line = status::status_line(&self.status_fields, &stats);
 
+        self.print_status_line(2, &line);
+    }
+    
+    fn print_status_line(&mut self, indent: usize, text: &str) {
         let Coord(x, y) = self.size - Coord(0, 1);
-        with_color(OVERLAY_1,||{
-            ncurses::mvaddstr(
-                y as i32, 0,
-                std::iter::repeat(' ').take(x).collect::<String>().as_str(),
-            );
-            ncurses::mvaddstr(
-                y as i32, 2,
-                message.as_str(),
-            );
-        });
+        let style = Style::from(Paint::Overlay);
+
+        self.renderer.put_str(
+            Coord(0, y),
+            &" ".repeat(x),
+            style,
+        );
+        // Whatever doesn't fit is cut off
+        let text: String = text.chars().take(x.saturating_sub(indent)).collect();
+        self.renderer.put_str(
+            Coord(indent, y),
+            &text,
+            style,
+        );
     }
     
-    fn mouse_click_event(&mut self, game: &mut Game) {
-        let mut mouse_event: ncurses::MEVENT = unsafe { mem::uninitialized() };
-        ncurses::getmouse(&mut mouse_event as *mut ncurses::MEVENT);
-        
-        let mouse_coord = Coord(mouse_event.x as usize, mouse_event.y as usize);
-        let real_coord = self.screen_to_world_space(mouse_coord);
+    fn read_line(&mut self, prefix: &str) -> Option<String> {
+        let mut line = String::new();
         
-        if (mouse_event.bstate & ncurses::BUTTON1_PRESSED as ncurses::mmask_t) != 0 {
-            // Spreading click cascade
-            let mut to_click = vec![real_coord];
+        loop {
+            self.print_status_line(0, &format!("{}{}", prefix, line));
+            self.renderer.refresh();
             
-            while let Some(fringe) = game.touch(&to_click) {
-                to_click = fringe;
-                self.print_chunks (game);
-                self.print_overlay(game);
-                ncurses::refresh();
-                thread::sleep(self.spread_delay);
+            match self.input.next_event() {
+                Event::Key(Key::Escape) | Event::Quit => return None,
+                Event::Key(Key::Enter) => return Some(line),
+                Event::Key(Key::Backspace) => { line.pop(); },
+                Event::Key(Key::Char(character)) => line.push(character),
+                _ => (),
             }
-        } else if (mouse_event.bstate & ncurses::BUTTON3_PRESSED as ncurses::mmask_t) != 0 {
-            game.toggle_flag(real_coord);
         }
     }
     
-    fn arrow_key_event(&mut self, arrow: i32) {
-        use ncurses::*;
-        self.scroll += match arrow {
-            KEY_UP    => Coord( 0, -1),
-            KEY_DOWN  => Coord( 0,  1),
-            KEY_LEFT  => Coord(-1,  0),
-            KEY_RIGHT => Coord( 1,  0),
-            _ => unreachable!(),
+    fn command_event(&mut self, game: &mut Game) {
+        let input = match self.read_line(PROMPT_PREFIX) {
+            Some(input) => input,
+            None => return,
         };
+        
+        let target = match command::parse(&input) {
+            Ok(Command::Goto(world_coord)) => Some(world_coord),
+            // Far enough out, the chunk's center is past the ends of the
+            // coordinates, and so out of reach too
+            Ok(Command::GotoChunk(chunk)) => {
+                let center = |x: isize| x.checked_mul(CHUNK.0).and_then(|x| x.checked_add(CHUNK.0 / 2));
+                Some(match (center(chunk.0), center(chunk.1)) {
+                    (Some(x), Some(y)) => Coord(x, y),
+                    _ => Coord::squared(isize::MAX),
+                })
+            },
+            Ok(Command::Mark(name)) => {
+                game.set_bookmark(name, self.view_center());
+                None
+            },
+            Ok(Command::Unmark(name)) => {
+                if game.remove_bookmark(&name).is_none() {
+                    self.message = Some(format!("No bookmark named '{}'", name));
+                }
+                None
+            },
+            Ok(Command::Jump(name)) => {
+                let target = game.bookmark(&name);
+                if target.is_none() {
+                    self.message = Some(format!("No bookmark named '{}'", name));
+                }
+                target
+            },
+            Ok(Command::Spawn) => Some(Coord::default()),
+            Ok(Command::Unfinished) => {
+                let target = game.nearest_unfinished(self.view_center());
+                if target.is_none() {
+                    self.message = Some("No unfinished chunks".to_owned());
+                }
+                target
+            },
+            Ok(Command::Explosion) => {
+                let target = game.last_explosion();
+                if target.is_none() {
+                    self.message = Some("Nothing has exploded yet".to_owned());
+                }
+                target
+            },
+            Ok(Command::Status(StatusSetting::Show)) => {
+                self.status_hidden = false;
+                None
+            },
+            Ok(Command::Status(StatusSetting::Hide)) => {
+                self.status_hidden = true;
+                None
+            },
+            Ok(Command::Status(StatusSetting::Fields(fields))) => {
+                self.status_fields = fields;
+                self.status_hidden = false;
+                None
+            },
+            Ok(Command::Theme(source)) => {
+                match theme::load(&source) {
+                    Ok(theme) => self.set_theme(&theme),
+                    Err(error) => self.message = Some(error),
+                }
+                None
+            },
+            Err(error) => {
+                self.message = Some(format!("{}: {}", error, input));
+                None
+            },
+        };
+        
+        // Leaves room for the view, and for moves made there
+        match target {
+            Some(world_coord) if game::within_reach(world_coord) => self.center_on(world_coord),
+            Some(_) => self.message = Some(format!("Out of reach: {}", input)),
+            None => (),
+        }
+    }
+    
+    fn mouse_event(&mut self, game: &mut Game, mouse: Mouse) -> Redraw {
+        let real_coord = self.screen_to_world_space(mouse.at);
+        
+        self.mouse = Some(mouse.at);
+        
+        if mouse.kind == MouseKind::Move {
+            if let Some(client) = self.remote.as_mut() {
+                client.move_cursor(real_coord);
+            }
+            if let Some(drag) = self.drag.as_mut() {
+                // Only consume whole squares so slow drags still accumulate
+                let delta = (drag.last - mouse.at.into()) / Coord(2, 1);
+                if delta != Coord::default() {
+                    drag.last = drag.last - delta * Coord(2, 1);
+                    drag.moved = true;
+                    return Redraw::Scroll(delta);
+                }
+            }
+            return Redraw::Overlay;
+        }
+        
+        self.message = None;
+        
+        match mouse.kind {
+            MouseKind::Press(Button::Left) => {
+                self.drag = Some(Drag { last: mouse.at.into(), moved: false });
+            },
+            MouseKind::Release(Button::Left) => {
+                // A press and release without panning in between is a click
+                if let Some(Drag { moved: false, .. }) = self.drag.take() {
+                    match self.remote.as_mut() {
+                        Some(client) => client.touch(real_coord),
+                        None => self.click_cascade(game, real_coord),
+                    }
+                }
+            },
+            MouseKind::Press(Button::Right) => match self.remote.as_mut() {
+                Some(client) => client.toggle_flag(real_coord),
+                None => game.toggle_flag(real_coord),
+            },
+            MouseKind::WheelUp   => return Redraw::Scroll(wheel_delta(mouse.shift, -WHEEL_STEP)),
+            MouseKind::WheelDown => return Redraw::Scroll(wheel_delta(mouse.shift,  WHEEL_STEP)),
+            _ => (),
+        }
+        
+        Redraw::Chunks
+    }
+    
+    fn click_cascade(&mut self, game: &mut Game, real_coord: Coord<isize>) {
+        // Spreading click cascade, drawn a wave at a time
+        let mut spreading = game.touch(&[real_coord]);
+        
+        while spreading {
+            self.render_partial(game);
+            thread::sleep(self.spread_delay);
+            spreading = game.spread();
+        }
+    }
+    
+    fn scroll_key_delta(&self, key: Key) -> Option<Coord<isize>> {
+        let Coord(chunk_x, chunk_y) = CHUNK;
+        let Coord(page_x, page_y) = Coord::<isize>::from(self.view_squares());
+        
+        Some(match key {
+            Key::Up         => Coord( 0, -1),
+            Key::Down       => Coord( 0,  1),
+            Key::Left       => Coord(-1,  0),
+            Key::Right      => Coord( 1,  0),
+            Key::ShiftUp    => Coord( 0, -chunk_y),
+            Key::ShiftDown  => Coord( 0,  chunk_y),
+            Key::ShiftLeft  => Coord(-chunk_x, 0),
+            Key::ShiftRight => Coord( chunk_x, 0),
+            Key::PageUp     => Coord( 0, -page_y),
+            Key::PageDown   => Coord( 0,  page_y),
+            Key::Home       => Coord(-page_x, 0),
+            Key::End        => Coord( page_x, 0),
+            _ => return None,
+        })
+    }
+    
+    // Fold already-queued scroll keys into one redraw
+    fn coalesce_scroll_keys(&mut self, mut delta: Coord<isize>) -> Coord<isize> {
+        while let Some(event) = self.input.poll_event() {
+            match event {
+                Event::Key(key) if self.scroll_key_delta(key).is_some() => {
+                    delta += self.scroll_key_delta(key).unwrap();
+                },
+                _ => {
+                    self.pending = Some(event);
+                    break;
+                },
+            }
+        }
+        delta
+    }
+    
+    fn view_center(&self) -> Coord<isize> {
+        self.screen_to_world_space(self.size / Coord(2, 2))
+    }
+    
+    fn center_on(&mut self, world_coord: Coord<isize>) {
+        self.scroll = world_coord - Coord::from(self.size / Coord(4, 2));
     }
     
     fn screen_to_world_space(&self, coord: Coord<usize>) -> Coord<isize> {
         self.scroll + Coord::from(coord/Coord(2,1))
     }
     
+    // Only for squares on screen, since nothing else has a screen position
     fn world_to_screen_space(&self, coord: Coord<isize>) -> Coord<usize> {
-        ((coord - self.scroll) * Coord(2,1)).into()
+        let Coord(x, y) = coord - self.scroll;
+        debug_assert!(x >= 0 && y >= 0, "{:?} is off screen", coord);
+        
+        (Coord(x, y) * Coord(2,1)).into()
     }
 }
 
-impl Drop for Interface {
-    fn drop(&mut self) {
-        ncurses::endwin();
-    }
+fn wheel_delta(horizontal: bool, step: isize) -> Coord<isize> {
+    if horizontal { Coord(step, 0) } else { Coord(0, step) }
 }
 
-#[inline]
-fn with_color<F>(color: i16, func: F) where F: Fn() {
-    ncurses::attron(COLOR_PAIR(color));
-    func();
-    ncurses::attroff(COLOR_PAIR(color));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::backend::memory::{MemoryScreen, ScriptedInput};
+    use crate::{game::chunk::Difficulty, net::localhost};
+
+    const SIZE: Coord<usize> = Coord(40, 12);
+
+    fn interface(events: Vec<Event>) -> Interface<MemoryScreen, ScriptedInput> {
+        let mut interface = Interface::new(
+            MemoryScreen::new(SIZE),
+            ScriptedInput::new(events),
+            Charset::Ascii,
+        );
+        interface.spread_delay = Duration::default();
+        interface.status_fields = vec![status::Field::Solved, status::Field::Exploded];
+        interface
+    }
+
+    fn key(key: Key) -> Event { Event::Key(key) }
+
+    fn mouse(x: usize, y: usize, kind: MouseKind) -> Event {
+        Event::Mouse(Mouse { at: Coord(x, y), kind, shift: false })
+    }
+
+    fn paints(screen: &MemoryScreen) -> Vec<Paint> {
+        let mut paints = Vec::new();
+        for y in 0..SIZE.1 {
+            for x in 0..SIZE.0 {
+                paints.extend(screen.cell(Coord(x, y)).style.map(|style| style.paint));
+            }
+        }
+        paints
+    }
+
+    #[test]
+    fn click_reveals() {
+        let mut interface = interface(vec![
+            mouse(20, 6, MouseKind::Press(Button::Left)),
+            mouse(20, 6, MouseKind::Release(Button::Left)),
+        ]);
+        interface.play(Game::with_settings(7, Difficulty::Normal));
+
+        match interface.renderer.cell(Coord(21, 6)).style.map(|style| style.paint) {
+            Some(Paint::Digit(_)) => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(interface.renderer.row(SIZE.1 - 1).contains("Solved: 0 | Exploded: 0"));
+    }
+
+    #[test]
+    fn drag_pans_without_clicking() {
+        let mut interface = interface(vec![
+            mouse(20, 6, MouseKind::Press(Button::Left)),
+            mouse(16, 4, MouseKind::Move),
+            mouse(16, 4, MouseKind::Release(Button::Left)),
+        ]);
+        interface.play(Game::with_settings(7, Difficulty::Normal));
+
+        assert_eq!(interface.scroll, Coord(2, 2));
+        let untouched = [Paint::Checker(0), Paint::Checker(1), Paint::Overlay];
+        assert!(paints(&interface.renderer).iter().all(|paint| untouched.contains(paint)));
+    }
+
+    #[test]
+    fn out_of_reach() {
+        let command = |text: &str| {
+            let mut events = vec![key(Key::Char(':'))];
+            events.extend(text.chars().map(|ch| key(Key::Char(ch))));
+            events.push(key(Key::Enter));
+            events
+        };
+        for &text in ["9223372036854775807 0", "0,-9223372036854775808", "c 1152921504606846975 0"].iter() {
+            let mut interface = interface(command(text));
+            interface.play(Game::with_settings(7, Difficulty::Normal));
+
+            assert_eq!(interface.scroll, Coord::default(), "{}", text);
+            assert!(interface.renderer.text().contains(&format!("Out of reach: {}", text)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn scrolling_matches_full_redraw() {
+        let mut events = vec![
+            mouse(20, 6, MouseKind::Press(Button::Left)),
+            mouse(20, 6, MouseKind::Release(Button::Left)),
+        ];
+        let mut redraw = events.clone();
+
+        // Ticks keep each scroll from being coalesced with the next one
+        for &scroll in [Key::ShiftDown, Key::ShiftLeft, Key::Up, Key::Down, Key::ShiftLeft].iter() {
+            events.extend(vec![key(scroll), Event::Tick]);
+        }
+        let mut scrolled = interface(events);
+        scrolled.play(Game::with_settings(3, Difficulty::Normal));
+
+        // Centering on a coordinate always redraws everything
+        redraw.push(key(Key::Char(':')));
+        redraw.extend("-6,14".chars().map(|ch| key(Key::Char(ch))));
+        redraw.push(key(Key::Enter));
+        let mut redrawn = interface(redraw);
+        redrawn.play(Game::with_settings(3, Difficulty::Normal));
+
+        assert_eq!(scrolled.scroll, Coord(-16, 8));
+        assert_eq!(redrawn.scroll, scrolled.scroll);
+        assert_eq!(paints(&scrolled.renderer), paints(&redrawn.renderer));
+        assert_eq!(scrolled.renderer.text(), redrawn.renderer.text());
+    }
+
+    #[test]
+    fn drawn_exactly_on_screen() {
+        let mut game = Game::with_settings(11, Difficulty::Easy);
+        for &point in [Coord(0, 0), Coord(-9, -9), Coord(17, -4), Coord(-3, 12)].iter() {
+            game.touch(&[point]);
+            while game.spread() {}
+        }
+
+        for &size in [SIZE, Coord(7, 3), Coord(81, 25)].iter() {
+            for y in -20..20 {
+                for x in (-30..30).step_by(3) {
+                    let mut interface = Interface::new(MemoryScreen::new(size), ScriptedInput::new(vec![]), Charset::Ascii);
+                    interface.scroll = Coord(x, y);
+                    interface.render_full(&game);
+                    interface.scroll_by(&mut game, Coord(1, -1));
+
+                    let screen = &interface.renderer;
+                    assert_eq!(screen.out_of_bounds, 0, "drew off screen at {:?}", interface.scroll);
+
+                    let view = Coord::<isize>::from(interface.view_squares());
+                    for square in IndexIterSigned::new(view, Coord::default()) {
+                        let AbsoluteCoord { chunk, square: within } = (interface.scroll + square).into();
+                        let expected = game.get_chunk(chunk).map(|chunk| chunk.view(within));
+                        let paint = screen.cell(Coord::from(square * Coord(2, 1))).style.map(|style| style.paint);
+
+                        let matches = match (&expected, paint) {
+                            (Some(game::SquareView::Clicked(n)), Some(Paint::Digit(digit))) => *n == digit,
+                            (Some(game::SquareView::Penalty), Some(Paint::Penalty)) => true,
+                            (Some(game::SquareView::Points), Some(Paint::Points)) => true,
+                            (Some(game::SquareView::Unclicked), Some(Paint::Checker(_))) => true,
+                            (Some(game::SquareView::Flagged), Some(Paint::Checker(_))) => true,
+                            (None, Some(Paint::Checker(_))) => true,
+                            _ => false,
+                        };
+                        assert!(matches, "{:?} drawn as {:?} at {:?}", expected, paint, interface.scroll + square);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_changes_are_sent() {
+        let click = vec![
+            mouse(20, 6, MouseKind::Press(Button::Left)),
+            mouse(20, 6, MouseKind::Release(Button::Left)),
+        ];
+        let sent = |events: Vec<Event>| {
+            let mut interface = interface(events);
+            interface.play(Game::with_settings(7, Difficulty::Normal));
+            interface.renderer.sent
+        };
+
+        let opened = sent(click.clone());
+        let flagged = sent([click.clone(), vec![mouse(4, 2, MouseKind::Press(Button::Right))]].concat());
+        let scrolled = sent([click, vec![key(Key::Up)]].concat());
+
+        // A flag is a single square, two cells wide
+        assert_eq!(flagged - opened, 2);
+        // The terminal scrolls the rest, leaving one new row to draw
+        assert_eq!(scrolled - opened, SIZE.0);
+    }
+
+    #[test]
+    fn partial_redraw_matches_full() {
+        let mut events = Vec::new();
+        for &(x, y) in [(20, 6), (2, 1), (36, 9), (8, 2), (30, 3)].iter() {
+            events.push(mouse(x, y, MouseKind::Press(Button::Left)));
+            events.push(mouse(x, y, MouseKind::Release(Button::Left)));
+        }
+        events.push(mouse(4, 9, MouseKind::Press(Button::Right)));
+        events.push(mouse(12, 4, MouseKind::Press(Button::Right)));
+        let mut partial = interface(events.clone());
+        partial.play(Game::with_settings(5, Difficulty::Hard));
+
+        // Jumping to where the view already is just redraws everything
+        events.push(key(Key::Char(':')));
+        events.extend("10,6".chars().map(|ch| key(Key::Char(ch))));
+        events.push(key(Key::Enter));
+        let mut full = interface(events);
+        full.play(Game::with_settings(5, Difficulty::Hard));
+
+        assert_eq!(full.scroll, Coord(0, 0));
+        assert_eq!(paints(&partial.renderer), paints(&full.renderer));
+        assert_eq!(partial.renderer.text(), full.renderer.text());
+    }
+
+    #[test]
+    fn online() {
+        let address = localhost::serve(Mode::Cooperative);
+        let mut ann = Client::connect(address, "ann").unwrap();
+        let mut bob = Client::connect(address, "bob").unwrap();
+        let (mut ann_game, mut bob_game) = (ann.game(), bob.game());
+        ann.view(Coord(0, 0), Coord(3, 2));
+        bob.view(Coord(0, 0), Coord(3, 2));
+
+        bob.touch(Coord(3, 3));
+        bob.move_cursor(Coord(5, 2));
+        let bob_id = bob.id();
+        localhost::sync_until(&mut ann, &mut ann_game, |client, game| {
+            game.get_chunk(Coord(0, 0)).is_some() && client.cursor_at(Coord(5, 2)) == Some(bob_id)
+        });
+
+        // Clicks go to the server rather than the local game
+        let mut interface = interface(vec![
+            mouse(30, 8, MouseKind::Press(Button::Left)),
+            mouse(30, 8, MouseKind::Release(Button::Left)),
+        ]);
+        interface.status_fields.clear();
+        interface.connect(ann);
+        interface.play(ann_game);
+        localhost::sync_until(&mut bob, &mut bob_game, |_, game| game.get_chunk(Coord(1, 1)).is_some());
+
+        let screen = &interface.renderer;
+        let slot = ((bob_id - 1) as usize % PLAYER_COLORS) as u8;
+        assert_eq!(screen.cell(Coord(10, 2)).style.map(|style| style.paint), Some(Paint::Player(slot)));
+        assert_eq!(screen.cell(Coord(6, 3)).style.map(|style| style.paint), Some(Paint::Digit(0)));
+        assert_eq!(screen.row(SIZE.1 - 1).trim(), "Players: ann 0/0, bob 0/0");
+    }
+
+    #[test]
+    fn following() {
+        let address = localhost::serve(Mode::Cooperative);
+        let mut ann = Client::connect(address, "ann").unwrap();
+        let mut watcher = Client::watch(address).unwrap();
+        let mut game = watcher.game();
+
+        ann.move_cursor(Coord(100, -50));
+        let ann_id = ann.id();
+        localhost::sync_until(&mut watcher, &mut game, |client, _| client.cursor_at(Coord(100, -50)) == Some(ann_id));
+
+        // Follow ann, then go back to a free camera
+        let mut interface = interface(vec![key(Key::Char('f')), Event::Tick, key(Key::Char('f'))]);
+        interface.connect(watcher);
+        interface.play(game);
+
+        assert_eq!(interface.follow, None);
+        assert_eq!(interface.scroll, Coord(100, -50) - Coord(10, 6));
+
+        let screen = &interface.renderer;
+        let slot = ((ann_id - 1) as usize % PLAYER_COLORS) as u8;
+        assert_eq!(screen.cell(Coord(20, 6)).style.map(|style| style.paint), Some(Paint::Player(slot)));
+        assert!(screen.row(SIZE.1 - 1).contains("Free camera"));
+    }
 }
@@ -0,0 +1,125 @@
+// Golden-file tests for what the interface draws. Run with
+// `UPDATE_SNAPSHOTS=1 cargo test` to rewrite the fixtures after an
+// intended change, then review the diff.
+
+use super::{
+    Interface,
+    backend::{Paint, memory::{MemoryScreen, ScriptedInput}},
+    glyphs::Charset,
+    status::Field,
+};
+use crate::{
+    aux::coord::Coord,
+    game::{Game, chunk::Difficulty},
+};
+
+use std::{env, fs, path::PathBuf};
+
+const SEED: u64 = 0x5eed;
+
+fn seeded_game() -> Game {
+    let mut game = Game::with_settings(SEED, Difficulty::Normal);
+
+    // A sparse lattice of clicks, so some chunks cascade and some explode
+    for y in (-12..12).step_by(5) {
+        for x in (-30..30).step_by(7) {
+            game.touch(&[Coord(x, y)]);
+            while game.spread() {}
+        }
+    }
+    game.toggle_flag(Coord(-9, -9));
+    game.toggle_flag(Coord(12, 3));
+
+    game
+}
+
+fn paint_char(paint: Option<Paint>) -> char {
+    match paint {
+        None => ' ',
+        Some(Paint::Checker(0)) => '.',
+        Some(Paint::Checker(_)) => ':',
+        Some(Paint::Overlay) => '=',
+        Some(Paint::Points)  => 'P',
+        Some(Paint::Penalty) => 'X',
+        Some(Paint::Digit(digit)) => (b'0' + digit) as char,
+        Some(Paint::Player(slot)) => (b'a' + slot) as char,
+    }
+}
+
+// The characters on screen, then which paint each cell was drawn with
+fn render(game: &Game, size: Coord<usize>, scroll: Coord<isize>) -> String {
+    let mut screen = MemoryScreen::new(size);
+    screen.mono = true;
+
+    let mut interface = Interface::new(screen, ScriptedInput::new(vec![]), Charset::Ascii);
+    interface.status_fields = vec![Field::Solved, Field::Exploded, Field::Score];
+    interface.scroll = scroll;
+    interface.render_full(game);
+
+    let screen = &interface.renderer;
+    assert_eq!(screen.out_of_bounds, 0, "drew off screen at {:?}", scroll);
+
+    let mut snapshot = screen.text();
+    snapshot.push('\n');
+    for y in 0..size.1 {
+        snapshot.extend((0..size.0).map(|x| paint_char(screen.cell(Coord(x, y)).style.map(|style| style.paint))));
+        snapshot.push('\n');
+    }
+    snapshot
+}
+
+fn check(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", &format!("{}.txt", name)]
+        .iter()
+        .collect();
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!(
+        "missing snapshot {}; run with UPDATE_SNAPSHOTS=1 to create it",
+        path.display(),
+    ));
+    assert!(
+        expected == actual,
+        "snapshot {} differs; run with UPDATE_SNAPSHOTS=1 to accept\n--- expected\n{}\n--- actual\n{}",
+        name, expected, actual,
+    );
+}
+
+#[test]
+fn origin() {
+    check("origin", &render(&seeded_game(), Coord(40, 12), Coord(0, 0)));
+}
+
+#[test]
+fn negative_scroll() {
+    let game = seeded_game();
+    check("negative_scroll", &render(&game, Coord(40, 12), Coord(-13, -5)));
+    check("negative_aligned", &render(&game, Coord(40, 12), Coord(-24, -8)));
+}
+
+#[test]
+fn positive_scroll() {
+    let game = seeded_game();
+    check("positive_scroll", &render(&game, Coord(40, 12), Coord(13, 5)));
+    check("positive_scroll_wide", &render(&game, Coord(64, 12), Coord(3, 1)));
+}
+
+#[test]
+fn mixed_scroll() {
+    let game = seeded_game();
+    check("mixed_scroll", &render(&game, Coord(40, 12), Coord(16, -9)));
+    check("mixed_scroll_tall", &render(&game, Coord(40, 20), Coord(-37, 3)));
+}
+
+#[test]
+fn sizes() {
+    let game = seeded_game();
+    check("size_odd_width", &render(&game, Coord(81, 25), Coord(-20, -10)));
+    check("size_tiny", &render(&game, Coord(7, 3), Coord(-2, 2)));
+}
+
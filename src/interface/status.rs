@@ -0,0 +1,198 @@
+use crate::{
+    aux::coord::Coord,
+    game::{self, Game, AbsoluteCoord},
+    net::{client::Player, protocol::PlayerId},
+};
+
+use std::{collections::BTreeMap, time::Duration};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Field {
+    Position,
+    Solved,
+    Exploded,
+    Allocated,
+    Ratio,
+    Score,
+    Time,
+    Difficulty,
+    Seed,
+    Players,
+    Scoreboard,
+}
+
+impl Field {
+    pub const ALL: [Field; 11] = [
+        Field::Position,
+        Field::Solved,
+        Field::Exploded,
+        Field::Allocated,
+        Field::Ratio,
+        Field::Score,
+        Field::Time,
+        Field::Difficulty,
+        Field::Seed,
+        Field::Players,
+        Field::Scoreboard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Position   => "position",
+            Field::Solved     => "solved",
+            Field::Exploded   => "exploded",
+            Field::Allocated  => "allocated",
+            Field::Ratio      => "ratio",
+            Field::Score      => "score",
+            Field::Time       => "time",
+            Field::Difficulty => "difficulty",
+            Field::Seed       => "seed",
+            Field::Players    => "players",
+            Field::Scoreboard => "scoreboard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Field::ALL.iter().cloned().find(|field| field.name() == name)
+    }
+}
+
+pub const DEFAULT_FIELDS: [Field; 5] = [
+    Field::Position,
+    Field::Solved,
+    Field::Exploded,
+    Field::Score,
+    Field::Time,
+];
+
+pub struct Stats<'a> {
+    pub game: &'a Game,
+    pub position: Option<Coord<isize>>,
+    pub elapsed: Duration,
+    // Everyone in a multiplayer game
+    pub players: Option<&'a BTreeMap<PlayerId, Player>>,
+}
+
+pub fn status_line(fields: &[Field], stats: &Stats) -> String {
+    fields
+        .iter()
+        .map(|&field| format_field(field, stats))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn format_field(field: Field, stats: &Stats) -> String {
+    let game = stats.game;
+
+    match field {
+        Field::Position => match stats.position {
+            Some(world_coord) => {
+                let AbsoluteCoord { chunk, square } = world_coord.into();
+                format!("At: {} Chunk: {} {}", world_coord, chunk, square)
+            },
+            None => "At: -".to_owned(),
+        },
+        Field::Solved    => format!("Solved: {}", game.chunks_won()),
+        Field::Exploded  => format!("Exploded: {}", game.chunks_lost()),
+        Field::Allocated => format!("Allocated: {}", game.allocated()),
+        Field::Ratio => match game.solve_ratio() {
+            Some(ratio) => format!("Ratio: {:.0}%", ratio * 100.0),
+            None => "Ratio: -".to_owned(),
+        },
+        Field::Score      => format!("Score: {}", game.score()),
+        Field::Time       => format!("Time: {}", format_duration(stats.elapsed)),
+        Field::Difficulty => format!("Difficulty: {}", game.difficulty().name()),
+        Field::Seed       => format!("Seed: {}", game.seed()),
+        // Chunks each player solved and exploded
+        Field::Players => match stats.players {
+            Some(players) => {
+                let players: Vec<_> = players
+                    .values()
+                    .map(|player| format!("{} {}/{}", player.name, player.solved, player.exploded))
+                    .collect();
+                format!("Players: {}", players.join(", "))
+            },
+            None => "Players: -".to_owned(),
+        },
+        // Players by points, with fewer explosions breaking ties and then
+        // whoever joined first
+        Field::Scoreboard => match stats.players {
+            Some(players) => {
+                let mut ranked: Vec<_> = players.values().collect();
+                ranked.sort_by_key(|player| (-game::points(player.solved, player.exploded), player.exploded));
+
+                let ranked: Vec<_> = ranked
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, player)| {
+                        format!("{}. {} {}", rank + 1, player.name, game::points(player.solved, player.exploded))
+                    })
+                    .collect();
+                format!("Scoreboard: {}", ranked.join(", "))
+            },
+            None => "Scoreboard: -".to_owned(),
+        },
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::chunk::Difficulty;
+
+    #[test]
+    fn field_names() {
+        for &field in Field::ALL.iter() {
+            assert_eq!(Field::from_name(field.name()), Some(field));
+        }
+        assert_eq!(Field::from_name("bogus"), None);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_secs(0)),    "0:00");
+        assert_eq!(format_duration(Duration::from_secs(75)),   "1:15");
+        assert_eq!(format_duration(Duration::from_secs(3601)), "1:00:01");
+    }
+
+    #[test]
+    fn line() {
+        let game = Game::with_settings(42, Difficulty::Hard);
+        let stats = Stats {
+            game: &game,
+            position: Some(Coord(-1, 9)),
+            elapsed: Duration::from_secs(5),
+            players: None,
+        };
+
+        assert_eq!(
+            status_line(&[Field::Position, Field::Ratio, Field::Seed, Field::Difficulty], &stats),
+            "At: (-1, 9) Chunk: (-1, 1) (7, 1) | Ratio: - | Seed: 42 | Difficulty: hard",
+        );
+        assert_eq!(status_line(&[], &stats), "");
+        assert_eq!(status_line(&[Field::Players], &stats), "Players: -");
+
+        let mut players = BTreeMap::new();
+        players.insert(2, Player { name: "bob".to_owned(), solved: 1, exploded: 2, ..Player::default() });
+        players.insert(1, Player { name: "ann".to_owned(), solved: 3, ..Player::default() });
+        players.insert(3, Player { name: "cat".to_owned(), solved: 3, exploded: 1, ..Player::default() });
+        players.insert(4, Player { name: "dan".to_owned(), solved: 3, ..Player::default() });
+        let stats = Stats { players: Some(&players), ..stats };
+        assert_eq!(status_line(&[Field::Players], &stats), "Players: ann 3/0, bob 1/2, cat 3/1, dan 3/0");
+        assert_eq!(
+            status_line(&[Field::Scoreboard], &stats),
+            "Scoreboard: 1. ann 30, 2. dan 30, 3. cat 25, 4. bob 0",
+        );
+    }
+}
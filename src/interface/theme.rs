@@ -0,0 +1,395 @@
+use std::fs;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Color {
+    Basic(i16),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pair {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub checker: [Pair; 2],
+    pub overlay: Pair,
+    pub points: Pair,
+    pub penalty: Pair,
+    pub clicked: Pair,
+    pub digits: [Color; 9],
+    // Other players' cursors, in the order they joined
+    pub players: [Pair; PLAYER_COLORS],
+}
+
+pub const PLAYER_COLORS: usize = 6;
+
+const BLACK:   Color = Color::Basic(0);
+const RED:     Color = Color::Basic(1);
+const GREEN:   Color = Color::Basic(2);
+const YELLOW:  Color = Color::Basic(3);
+const BLUE:    Color = Color::Basic(4);
+const MAGENTA: Color = Color::Basic(5);
+const CYAN:    Color = Color::Basic(6);
+const WHITE:   Color = Color::Basic(7);
+
+const BASIC_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+// xterm's default values for the eight basic colors
+const BASIC_RGB: [(u8, u8, u8); 8] = [
+    (  0,   0,   0),
+    (205,   0,   0),
+    (  0, 205,   0),
+    (205, 205,   0),
+    (  0,   0, 238),
+    (205,   0, 205),
+    (  0, 205, 205),
+    (229, 229, 229),
+];
+
+const fn pair(fg: Color, bg: Color) -> Pair { Pair { fg, bg } }
+
+// Okabe-Ito again, which stays distinguishable under every theme
+const PLAYERS: [Pair; PLAYER_COLORS] = [
+    pair(BLACK, Color::Rgb(230, 159,   0)),
+    pair(BLACK, Color::Rgb( 86, 180, 233)),
+    pair(WHITE, Color::Rgb(  0, 158, 115)),
+    pair(WHITE, Color::Rgb(  0, 114, 178)),
+    pair(WHITE, Color::Rgb(213,  94,   0)),
+    pair(BLACK, Color::Rgb(204, 121, 167)),
+];
+
+pub const NAMES: [&str; 5] = ["classic", "dark", "contrast", "deuteranopia", "protanopia"];
+
+pub fn builtin(name: &str) -> Option<Theme> {
+    Some(match name {
+        "classic" => Theme {
+            checker: [pair(BLACK, WHITE), pair(BLACK, GREEN)],
+            overlay: pair(WHITE, BLACK),
+            points:  pair(BLACK, YELLOW),
+            penalty: pair(YELLOW, RED),
+            clicked: pair(WHITE, BLACK),
+            digits: [
+                WHITE, BLUE, GREEN, RED, MAGENTA,
+                Color::Rgb(175, 0, 0), CYAN, WHITE, Color::Rgb(128, 128, 128),
+            ],
+            players: PLAYERS,
+        },
+        "dark" => Theme {
+            checker: [
+                pair(WHITE, Color::Rgb(48, 48, 48)),
+                pair(WHITE, Color::Rgb(28, 58, 28)),
+            ],
+            overlay: pair(Color::Rgb(188, 188, 188), BLACK),
+            points:  pair(BLACK, Color::Rgb(175, 135, 0)),
+            penalty: pair(WHITE, Color::Rgb(135, 0, 0)),
+            clicked: pair(WHITE, BLACK),
+            digits: [
+                WHITE,
+                Color::Rgb(135, 175, 255), Color::Rgb(135, 215, 135), Color::Rgb(255, 135, 135),
+                Color::Rgb(175, 135, 255), Color::Rgb(215, 135,  95), Color::Rgb( 95, 215, 215),
+                Color::Rgb(215, 215, 215), Color::Rgb(138, 138, 138),
+            ],
+            players: PLAYERS,
+        },
+        "contrast" => Theme {
+            checker: [pair(BLACK, WHITE), pair(WHITE, BLACK)],
+            overlay: pair(BLACK, WHITE),
+            points:  pair(BLACK, YELLOW),
+            penalty: pair(WHITE, RED),
+            clicked: pair(WHITE, BLACK),
+            digits: [WHITE, CYAN, GREEN, YELLOW, MAGENTA, RED, CYAN, WHITE, WHITE],
+            players: PLAYERS,
+        },
+        // Okabe-Ito palette, avoiding red/green as the only distinguishing cue
+        "deuteranopia" => Theme {
+            checker: [pair(BLACK, WHITE), pair(BLACK, Color::Rgb(86, 180, 233))],
+            overlay: pair(WHITE, BLACK),
+            points:  pair(BLACK, Color::Rgb(240, 228, 66)),
+            penalty: pair(WHITE, Color::Rgb(213, 94, 0)),
+            clicked: pair(WHITE, BLACK),
+            digits: [
+                WHITE,
+                Color::Rgb( 86, 180, 233), Color::Rgb(230, 159,   0), Color::Rgb(213,  94,   0),
+                Color::Rgb(204, 121, 167), Color::Rgb(  0, 114, 178), Color::Rgb(  0, 158, 115),
+                Color::Rgb(240, 228,  66), Color::Rgb(153, 153, 153),
+            ],
+            players: PLAYERS,
+        },
+        // Reds read as dark to protanopes, so penalties use blue instead
+        "protanopia" => Theme {
+            checker: [pair(BLACK, WHITE), pair(BLACK, Color::Rgb(187, 187, 187))],
+            overlay: pair(WHITE, BLACK),
+            points:  pair(BLACK, Color::Rgb(240, 228, 66)),
+            penalty: pair(WHITE, Color::Rgb(0, 114, 178)),
+            clicked: pair(WHITE, BLACK),
+            digits: [
+                WHITE,
+                Color::Rgb( 86, 180, 233), Color::Rgb(230, 159,   0), Color::Rgb(204, 121, 167),
+                Color::Rgb(  0, 158, 115), Color::Rgb(240, 228,  66), Color::Rgb(  0, 114, 178),
+                Color::Rgb(255, 255, 255), Color::Rgb(153, 153, 153),
+            ],
+            players: PLAYERS,
+        },
+        _ => return None,
+    })
+}
+
+impl Default for Theme {
+    fn default() -> Self { builtin("classic").unwrap() }
+}
+
+// Builtin theme name, or path to a theme file
+pub fn load(source: &str) -> Result<Theme, String> {
+    if let Some(theme) = builtin(source) { return Ok(theme); }
+
+    let text = fs::read_to_string(source)
+        .map_err(|error| format!(
+            "Can't read theme {}: {} (builtin themes are {})",
+            source, error, NAMES.join(", "),
+        ))?;
+
+    parse(&text)
+}
+
+pub fn parse(text: &str) -> Result<Theme, String> {
+    let mut theme = Theme::default();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        parse_line(&mut theme, line).map_err(|error| format!("Line {}: {}", number + 1, error))?;
+    }
+
+    Ok(theme)
+}
+
+fn parse_line(theme: &mut Theme, line: &str) -> Result<(), String> {
+    let mut halves = line.splitn(2, '=');
+    let key = halves.next().unwrap_or("").trim();
+    let value = halves.next().ok_or("Expected key = value")?.trim();
+
+    match key {
+        "base"     => *theme = builtin(value).ok_or_else(|| format!("Unknown theme {}", value))?,
+        "checker1" => theme.checker[0] = parse_pair(value)?,
+        "checker2" => theme.checker[1] = parse_pair(value)?,
+        "overlay"  => theme.overlay    = parse_pair(value)?,
+        "points"   => theme.points     = parse_pair(value)?,
+        "penalty"  => theme.penalty    = parse_pair(value)?,
+        "clicked"  => theme.clicked    = parse_pair(value)?,
+        _ => if let Some(digit) = numbered(key, "digit", 8) {
+            theme.digits[digit] = parse_color(value)?;
+        } else if let Some(player) = numbered(key, "player", PLAYER_COLORS) {
+            theme.players[player - 1] = parse_pair(value)?;
+        } else {
+            return Err(format!("Unknown key {}", key));
+        },
+    }
+
+    Ok(())
+}
+
+// `digit3` or `player1`, counting from 1 up to `last`
+fn numbered(key: &str, prefix: &str, last: usize) -> Option<usize> {
+    key.strip_prefix(prefix)
+        .and_then(|number| number.parse::<usize>().ok())
+        .filter(|number| (1..=last).contains(number))
+}
+
+fn parse_pair(value: &str) -> Result<Pair, String> {
+    let colors = value
+        .split_whitespace()
+        .map(parse_color)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match colors.as_slice() {
+        &[fg, bg] => Ok(Pair { fg, bg }),
+        _ => Err(format!("Expected foreground and background: {}", value)),
+    }
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    if let Some(index) = BASIC_NAMES.iter().position(|&name| name == value) {
+        return Ok(Color::Basic(index as i16));
+    }
+
+    let invalid = || format!("Invalid color {}", value);
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() { return Err(invalid()); }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+
+    value.parse().map(Color::Indexed).map_err(|_| invalid())
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Depth {
+    Basic,
+    Indexed,
+    Rgb,
+}
+
+// Slots below this are left alone so the terminal's own palette survives
+const FIRST_CUSTOM: i16 = 16;
+
+// Maps theme colors to what the terminal can show. With every color
+// available, the slots from FIRST_CUSTOM up are redefined, so indexed
+// colors past the system ones get slots of their own holding the values
+// they usually have.
+pub struct Palette {
+    depth: Depth,
+    custom: Vec<(u8, u8, u8)>,
+}
+
+impl Palette {
+    pub fn new(depth: Depth) -> Self {
+        Palette { depth, custom: Vec::new() }
+    }
+
+    pub fn index(&mut self, color: Color) -> i16 {
+        match (color, self.depth) {
+            (Color::Basic(index), _) => index,
+            (Color::Indexed(index), Depth::Basic) => nearest_basic(indexed_rgb(index)),
+            (Color::Indexed(index), Depth::Rgb) if index as i16 >= FIRST_CUSTOM => self.custom(indexed_rgb(index)),
+            (Color::Indexed(index), _) => index as i16,
+            (Color::Rgb(r, g, b), Depth::Basic) => nearest_basic((r, g, b)),
+            (Color::Rgb(r, g, b), Depth::Indexed) => nearest_indexed((r, g, b)) as i16,
+            (Color::Rgb(r, g, b), Depth::Rgb) => self.custom((r, g, b)),
+        }
+    }
+
+    fn custom(&mut self, rgb: (u8, u8, u8)) -> i16 {
+        let slot = match self.custom.iter().position(|&custom| custom == rgb) {
+            Some(slot) => slot,
+            None => {
+                self.custom.push(rgb);
+                self.custom.len() - 1
+            },
+        };
+        FIRST_CUSTOM + slot as i16
+    }
+
+    // Slots that need redefining before the returned indices are valid
+    pub fn custom_colors<'a>(&'a self) -> impl Iterator<Item=(i16, (u8, u8, u8))> + 'a {
+        self.custom
+            .iter()
+            .enumerate()
+            .map(|(slot, &rgb)| (FIRST_CUSTOM + slot as i16, rgb))
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let square = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    square(r1, r2) + square(g1, g2) + square(b1, b2)
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> i16 {
+    (0..BASIC_RGB.len())
+        .min_by_key(|&index| distance(rgb, BASIC_RGB[index]))
+        .unwrap() as i16
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Standard xterm 256 color layout: 16 system colors, a 6x6x6 cube, then 24 grays
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=7  => BASIC_RGB[index as usize],
+        8..=15 => BASIC_RGB[index as usize - 8],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            )
+        },
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        },
+    }
+}
+
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255u8)
+        .min_by_key(|&index| distance(rgb, indexed_rgb(index)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins() {
+        for name in NAMES.iter() {
+            assert!(builtin(name).is_some(), "{}", name);
+        }
+        assert!(builtin("bogus").is_none());
+    }
+
+    #[test]
+    fn theme_file() {
+        let theme = parse("
+            # Comments and blank lines are ignored
+            base = contrast
+            penalty = white #870000
+            digit3 = 196
+            player2 = black yellow
+        ").unwrap();
+
+        let contrast = builtin("contrast").unwrap();
+        assert_eq!(theme.checker, contrast.checker);
+        assert_eq!(theme.penalty, Pair { fg: WHITE, bg: Color::Rgb(0x87, 0, 0) });
+        assert_eq!(theme.digits[3], Color::Indexed(196));
+        assert_eq!(theme.players[1], Pair { fg: BLACK, bg: YELLOW });
+
+        assert!(parse("digit9 = red").is_err());
+        assert!(parse("player7 = red black").is_err());
+        assert!(parse("player1 = red").is_err());
+        assert!(parse("points = red").is_err());
+        assert!(parse("points = red #12345").is_err());
+        assert!(parse("points = red #12345é").is_err());
+        assert!(parse("bogus = red black").is_err());
+        assert_eq!(parse("\n\nfoo").unwrap_err(), "Line 3: Expected key = value");
+    }
+
+    #[test]
+    fn palette_degrades() {
+        let orange = Color::Rgb(255, 135, 0);
+
+        assert_eq!(Palette::new(Depth::Basic).index(orange), 3);
+        assert_eq!(Palette::new(Depth::Indexed).index(orange), 208);
+        assert_eq!(Palette::new(Depth::Basic).index(Color::Indexed(196)), 1);
+        assert_eq!(Palette::new(Depth::Basic).index(BLUE), 4);
+
+        let mut palette = Palette::new(Depth::Rgb);
+        assert_eq!(palette.index(orange), 16);
+        assert_eq!(palette.index(Color::Rgb(1, 2, 3)), 17);
+        assert_eq!(palette.index(orange), 16);
+        assert_eq!(palette.index(Color::Indexed(16)), 18);
+        assert_eq!(palette.index(Color::Indexed(208)), 16);
+        assert_eq!(palette.index(Color::Indexed(9)), 9);
+        assert_eq!(
+            palette.custom_colors().collect::<Vec<_>>(),
+            vec![(16, (255, 135, 0)), (17, (1, 2, 3)), (18, (0, 0, 0))],
+        );
+    }
+
+    #[test]
+    fn indexed_palette() {
+        assert_eq!(indexed_rgb(16),  (0, 0, 0));
+        assert_eq!(indexed_rgb(196), (255, 0, 0));
+        assert_eq!(indexed_rgb(231), (255, 255, 255));
+        assert_eq!(indexed_rgb(232), (8, 8, 8));
+        assert_eq!(nearest_indexed((128, 128, 128)), 244);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod game;
+pub mod interface;
+pub mod net;
+pub mod aux;
+pub mod bot;
@@ -1,14 +1,198 @@
-#![feature(associated_type_defaults, const_fn, const_let)]
+use minesweepfinity::{
+    bot::Bot,
+    interface::{Interface, backend, glyphs::Charset, theme::{self, Theme}},
+    game::{Game, chunk::Difficulty, region::RegionStore},
+    net::{client::Client, server::Server},
+};
 
-mod game;
-mod interface;
-mod aux;
+use std::{env, io, net::Ipv4Addr, process, thread};
 
-use self::{
-    interface::Interface,
-    game::Game,
-};
+const USAGE: &str = "\
+Usage: minesweepfinity [--seed NUMBER] [--difficulty easy|normal|hard]
+                       [--theme NAME|PATH] [--mono] [--unicode]
+                       [--cache-size CHUNKS] [--region-dir PATH]
+                       [--workers NUMBER] [--broadcast ADDRESS [--name NAME]]
+       minesweepfinity --connect ADDRESS [--name NAME]
+                       [--theme NAME|PATH] [--mono] [--unicode]
+       minesweepfinity --watch ADDRESS
+                       [--theme NAME|PATH] [--mono] [--unicode]
+       minesweepfinity --headless [--seed NUMBER] [--difficulty easy|normal|hard]
+                       [--cache-size CHUNKS] [--region-dir PATH]";
+
+// Chunks kept in memory once paging is turned on
+const DEFAULT_CACHE_SIZE: usize = 4096;
+// Background threads generating chunks around the view
+const DEFAULT_WORKERS: usize = 2;
+
+struct Options {
+    game: Game,
+    theme: Theme,
+    charset: Charset,
+    mono: bool,
+    remote: Option<Client>,
+    headless: bool,
+}
 
 fn main() {
-    Interface::new().play(Game::new());
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(1);
+    });
+
+    if options.headless {
+        let mut bot = Bot::new(options.game);
+        if let Err(error) = bot.run(io::stdin().lock(), io::stdout().lock()) {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        save(bot.into_game());
+        return;
+    }
+    
+    // https://no-color.org
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+    let (screen, input) = backend::init(options.mono || no_color);
+    let mut interface = Interface::new(screen, input, options.charset);
+    interface.set_theme(&options.theme);
+    if let Some(client) = options.remote {
+        interface.connect(client);
+    }
+    let game = interface.play(options.game);
+    // Puts the terminal back first, so any error can be read
+    drop(interface);
+    save(game);
+}
+
+fn save(mut game: Game) {
+    if let Err(error) = game.save() {
+        eprintln!("Cannot save the world: {}", error);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> Result<Options, String> {
+    let mut seed = None;
+    let mut difficulty = None;
+    let mut theme = Theme::default();
+    let mut charset = Charset::default();
+    let mut mono = false;
+    let mut cache_size = None;
+    let mut region_dir = None;
+    let mut workers = None;
+    let mut connect = None;
+    let mut watch = None;
+    let mut broadcast = None;
+    let mut name = env::var("USER").unwrap_or_default();
+    let mut headless = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--seed" => {
+                let value = value()?;
+                seed = Some(value.parse().map_err(|_| format!("Invalid seed: {}", value))?);
+            },
+            "--difficulty" => {
+                let value = value()?;
+                difficulty = Some(Difficulty::from_name(&value)
+                    .ok_or_else(|| format!("Invalid difficulty: {}", value))?);
+            },
+            "--theme" => theme = theme::load(&value()?)?,
+            "--mono" => mono = true,
+            "--unicode" => charset = Charset::Unicode,
+            "--cache-size" => {
+                let value = value()?;
+                cache_size = Some(value.parse().map_err(|_| format!("Invalid cache size: {}", value))?);
+            },
+            "--region-dir" => region_dir = Some(value()?),
+            "--workers" => {
+                let value = value()?;
+                workers = Some(value.parse().map_err(|_| format!("Invalid number of workers: {}", value))?);
+            },
+            "--connect" => connect = Some(value()?),
+            "--watch" => watch = Some(value()?),
+            "--broadcast" => broadcast = Some(value()?),
+            "--name" => name = value()?,
+            "--headless" => headless = true,
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    let new_game = || -> Result<Game, String> {
+        let store = if cache_size.is_some() || region_dir.is_some() {
+            let store = match region_dir.clone() {
+                Some(dir) => RegionStore::open(dir),
+                None => RegionStore::temporary(),
+            };
+            Some(store.map_err(|error| format!("Cannot open region store: {}", error))?)
+        } else {
+            None
+        };
+
+        // A saved world is played on with its own settings
+        let saved = store.as_ref().and_then(RegionStore::world);
+        let seed = seed.or(saved.map(|world| world.seed)).unwrap_or_else(rand::random);
+        let difficulty = difficulty.or(saved.map(|world| world.difficulty)).unwrap_or_default();
+        let mut game = Game::with_settings(seed, difficulty);
+
+        if let Some(store) = store {
+            game.set_paging(store, cache_size.unwrap_or(DEFAULT_CACHE_SIZE))
+                .map_err(|error| format!("Cannot use region store: {}", error))?;
+        }
+
+        // Bots wait for every chunk anyway, so they'd gain nothing from workers
+        let workers = workers.unwrap_or(DEFAULT_WORKERS);
+        if workers > 0 && !headless {
+            game.set_generator(workers);
+        }
+        Ok(game)
+    };
+    if headless && (connect.is_some() || watch.is_some() || broadcast.is_some()) {
+        return Err("--headless plays a world of its own".to_owned());
+    }
+    if headless && workers.is_some() {
+        return Err("--headless generates chunks as they're needed".to_owned());
+    }
+    let cannot_connect = |address: &str, error| format!("Cannot connect to {}: {}", address, error);
+
+    // A server has its own world, so the settings for making one don't
+    // apply when joining one
+    let remote = if let Some(address) = connect {
+        Some(Client::connect(&address, &name).map_err(|error| cannot_connect(&address, error))?)
+    } else if let Some(address) = watch {
+        Some(Client::watch(&address).map_err(|error| cannot_connect(&address, error))?)
+    } else if let Some(address) = broadcast {
+        Some(host(&address, new_game()?, &name)?)
+    } else {
+        None
+    };
+
+    let game = match remote.as_ref() {
+        Some(client) => client.game(),
+        None => new_game()?,
+    };
+
+    Ok(Options {
+        game,
+        theme,
+        charset,
+        mono,
+        remote,
+        headless,
+    })
+}
+
+// Plays `game` through a server of our own, so others can watch
+fn host(address: &str, game: Game, name: &str) -> Result<Client, String> {
+    let server = Server::bind(address, game)
+        .map_err(|error| format!("Cannot listen on {}: {}", address, error))?;
+    let mut local = server.local_addr().map_err(|error| error.to_string())?;
+    if local.ip().is_unspecified() {
+        local.set_ip(Ipv4Addr::LOCALHOST.into());
+    }
+
+    thread::spawn(move || server.run());
+    Client::connect(local, name).map_err(|error| format!("Cannot connect to {}: {}", local, error))
 }
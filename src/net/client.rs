@@ -0,0 +1,200 @@
+use super::protocol::{Mode, PlayerId, Request, Update};
+use crate::{
+    aux::coord::Coord,
+    game::{Game, AbsoluteCoord, chunk::{self, Difficulty}},
+};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+#[derive(Default)]
+pub struct Player {
+    pub name: String,
+    pub cursor: Option<Coord<isize>>,
+    pub solved: u64,
+    pub exploded: u64,
+}
+
+// One player's end of a game hosted by a `Server`. Actions go to the
+// server, and what it sends back is mirrored into a local `Game`.
+// Spectators get the same, but their actions go nowhere.
+pub struct Client {
+    stream: TcpStream,
+    updates: Receiver<Update>,
+    id: PlayerId,
+    seed: u64,
+    difficulty: Difficulty,
+    mode: Mode,
+    players: BTreeMap<PlayerId, Player>,
+    view: Option<(Coord<isize>, Coord<isize>)>,
+    cursor: Option<Coord<isize>>,
+    connected: bool,
+    watching: bool,
+}
+
+impl Client {
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> io::Result<Self> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join("_");
+        let name = if name.is_empty() { "player".to_owned() } else { name };
+        Client::open(address, Some(Request::Hello(name)))
+    }
+
+    pub fn watch(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Client::open(address, None)
+    }
+
+    fn open(address: impl ToSocketAddrs, hello: Option<Request>) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        if let Some(hello) = hello.as_ref() {
+            writeln!(stream, "{}", hello.encode())?;
+        }
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let (id, seed, difficulty, mode) = match Update::decode(&line) {
+            Ok(Update::Welcome { id, seed, difficulty, mode }) => (id, seed, difficulty, mode),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a minesweepfinity server")),
+        };
+
+        let (sender, updates) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if let Ok(update) = Update::decode(&line) {
+                    if sender.send(update).is_err() { break; }
+                }
+            }
+        });
+
+        Ok(Client {
+            stream,
+            updates,
+            id,
+            seed,
+            difficulty,
+            mode,
+            players: BTreeMap::new(),
+            view: None,
+            cursor: None,
+            connected: true,
+            watching: hello.is_none(),
+        })
+    }
+
+    pub fn id(&self) -> PlayerId { self.id }
+    pub fn connected(&self) -> bool { self.connected }
+    pub fn mode(&self) -> Mode { self.mode }
+    pub fn watching(&self) -> bool { self.watching }
+    pub fn players(&self) -> &BTreeMap<PlayerId, Player> { &self.players }
+
+    // An empty game for `sync` to fill in, with the server's settings
+    pub fn game(&self) -> Game {
+        Game::with_settings(self.seed, self.difficulty)
+    }
+
+    pub fn touch(&mut self, world_coord: Coord<isize>) {
+        self.act(Request::Touch(world_coord));
+    }
+
+    pub fn toggle_flag(&mut self, world_coord: Coord<isize>) {
+        self.act(Request::Flag(world_coord));
+    }
+
+    // Only sent when it moves onto another square
+    pub fn move_cursor(&mut self, world_coord: Coord<isize>) {
+        if self.cursor.replace(world_coord) != Some(world_coord) {
+            self.act(Request::Cursor(world_coord));
+        }
+    }
+
+    // The chunks in `min..max` the server should keep us up to date on
+    pub fn view(&mut self, min: Coord<isize>, max: Coord<isize>) {
+        if self.view.replace((min, max)) != Some((min, max)) {
+            self.send(Request::View(min, max));
+        }
+    }
+
+    // Whose cursor is on the square, besides our own
+    pub fn cursor_at(&self, world_coord: Coord<isize>) -> Option<PlayerId> {
+        self.players
+            .iter()
+            .find(|&(&id, player)| id != self.id && player.cursor == Some(world_coord))
+            .map(|(&id, _)| id)
+    }
+
+    // Applies everything the server sent since last time to `game`, and
+    // returns the squares that may look different for it
+    pub fn sync(&mut self, game: &mut Game) -> Vec<Coord<isize>> {
+        let mut changed = Vec::new();
+
+        loop {
+            match self.updates.try_recv() {
+                Ok(update) => self.apply(game, update, &mut changed),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                },
+            }
+        }
+
+        changed
+    }
+
+    fn apply(&mut self, game: &mut Game, update: Update, changed: &mut Vec<Coord<isize>>) {
+        match update {
+            Update::Welcome { .. } => (),
+            Update::Joined(id, name) => self.players.entry(id).or_default().name = name,
+            Update::Left(id) => {
+                if let Some(player) = self.players.remove(&id) { changed.extend(player.cursor); }
+            },
+            Update::Cursor(id, world_coord) => {
+                let player = self.players.entry(id).or_default();
+                changed.extend(player.cursor.replace(world_coord));
+                changed.push(world_coord);
+            },
+            Update::Chunk { coord, outcome, views } => {
+                game.mirror_chunk(coord, &views, outcome);
+                changed.extend(chunk::all_squares().map(|square| Coord::from(AbsoluteCoord { chunk: coord, square })));
+            },
+            Update::Score { id, solved, exploded } => {
+                let player = self.players.entry(id).or_default();
+                player.solved = solved;
+                player.exploded = exploded;
+            },
+            Update::Totals { won, lost } => game.mirror_counts(won, lost),
+            // Only ever a move the interface wouldn't have made
+            Update::Error(_) => (),
+        }
+    }
+
+    fn act(&mut self, request: Request) {
+        if !self.watching { self.send(request); }
+    }
+
+    // A lost connection shows up in `sync`, once the reader notices
+    fn send(&mut self, request: Request) {
+        if writeln!(self.stream, "{}", request.encode()).is_err() {
+            self.connected = false;
+        }
+    }
+}
+
+// The reader thread holds its own handle on the connection, which would
+// otherwise keep it open
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
@@ -0,0 +1,270 @@
+// Whole sessions between a server and clients on this machine
+
+use super::{client::Client, protocol::Mode, server::Server};
+use crate::{
+    aux::{coord::Coord, index_iter::IndexIterSigned},
+    game::{Game, SquareView, AbsoluteCoord, chunk::{self, Difficulty}},
+};
+
+use std::{
+    net::SocketAddr,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SEED: u64 = 21;
+const VIEW: (Coord<isize>, Coord<isize>) = (Coord(-2, -2), Coord(3, 3));
+
+// A server for a fresh game, running until the tests finish
+pub fn serve(mode: Mode) -> SocketAddr {
+    let mut server = Server::bind("127.0.0.1:0", Game::with_settings(SEED, Difficulty::Normal)).unwrap();
+    server.set_mode(mode);
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+fn join(address: SocketAddr, name: &str) -> (Client, Game) {
+    let mut client = Client::connect(address, name).unwrap();
+    let game = client.game();
+    client.view(VIEW.0, VIEW.1);
+    (client, game)
+}
+
+// Syncs until `done` holds, giving up after a while
+pub fn sync_until(client: &mut Client, game: &mut Game, mut done: impl FnMut(&Client, &Game) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        client.sync(game);
+        if done(client, game) { return; }
+
+        assert!(Instant::now() < deadline, "timed out waiting for the server");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn play(game: &mut Game, world_coord: Coord<isize>) {
+    game.touch(&[world_coord]);
+    while game.spread() {}
+}
+
+// Whether `mirror` shows everything in view the way `expected` does.
+// Chunks nobody has touched yet are never sent.
+fn mirrors(mirror: &Game, expected: &Game) -> bool {
+    let views = |game: &Game, coord| game.get_chunk(coord).map(|chunk| {
+        chunk::all_squares().map(|square| chunk.view(square)).collect::<Vec<_>>()
+    });
+
+    IndexIterSigned::new(VIEW.1 - VIEW.0, VIEW.0).all(|coord| {
+        match (views(expected, coord), views(mirror, coord)) {
+            (Some(expected), Some(mirrored)) => expected == mirrored,
+            (Some(expected), None) => expected.iter().all(|&view| view == SquareView::Unclicked),
+            (None, mirrored) => mirrored.is_none(),
+        }
+    })
+}
+
+#[test]
+fn cooperative_session() {
+    let address = serve(Mode::Cooperative);
+    let mut expected = Game::with_settings(SEED, Difficulty::Normal);
+
+    let (mut ann, mut ann_game) = join(address, "ann");
+    let (mut bob, mut bob_game) = join(address, "bob smith");
+    assert_eq!(bob_game.seed(), SEED);
+
+    ann.touch(Coord(3, 3));
+    play(&mut expected, Coord(3, 3));
+    sync_until(&mut bob, &mut bob_game, |_, game| mirrors(game, &expected));
+
+    // Set off a mine in the chunk that was opened
+    let opened = &expected.chunks[&Coord(0, 0)];
+    let mine = chunk::all_squares().find(|&square| opened.mines.get(square)).unwrap();
+    let mine = Coord::from(AbsoluteCoord { chunk: Coord(0, 0), square: mine });
+    bob.touch(mine);
+    play(&mut expected, mine);
+    assert_eq!(expected.chunks_lost(), 1);
+
+    let bob_id = bob.id();
+    sync_until(&mut ann, &mut ann_game, |client, game| {
+        mirrors(game, &expected) && client.players().get(&bob_id).is_some_and(|bob| bob.exploded == 1)
+    });
+    assert_eq!(ann_game.chunks_lost(), 1);
+    assert_eq!(ann.players()[&bob_id].name, "bob_smith");
+    assert_eq!(ann.players()[&ann.id()].exploded, 0);
+
+    bob.move_cursor(Coord(-4, 7));
+    sync_until(&mut ann, &mut ann_game, |client, _| client.cursor_at(Coord(-4, 7)) == Some(bob_id));
+    assert_eq!(bob.cursor_at(Coord(-4, 7)), None);
+
+    // Someone joining late catches up on the world and who's in it
+    let (mut cat, mut cat_game) = join(address, "cat");
+    sync_until(&mut cat, &mut cat_game, |client, game| {
+        mirrors(game, &expected) && client.players().len() == 3 && client.cursor_at(Coord(-4, 7)) == Some(bob_id)
+    });
+    assert_eq!(cat_game.chunks_lost(), 1);
+    assert_eq!(cat.players()[&bob_id].exploded, 1);
+
+    drop(bob);
+    sync_until(&mut cat, &mut cat_game, |client, _| !client.players().contains_key(&bob_id));
+}
+
+#[test]
+fn stray_connections() {
+    let address = serve(Mode::Cooperative);
+
+    // Not speaking the protocol doesn't take the server down
+    let mut stray = std::net::TcpStream::connect(address).unwrap();
+    std::io::Write::write_all(&mut stray, b"nonsense\ntouch 1\n").unwrap();
+    let edges = format!("view {max} 0 {min} 1\nview 0 {min} 5 {max}\nview {max} {max} {max} {max}\n", min = isize::MIN, max = isize::MAX);
+    std::io::Write::write_all(&mut stray, edges.as_bytes()).unwrap();
+    drop(stray);
+
+    let (mut ann, mut ann_game) = join(address, "ann");
+    ann.touch(Coord(0, 0));
+    sync_until(&mut ann, &mut ann_game, |_, game| game.get_chunk(Coord(0, 0)).is_some());
+    assert!(ann.connected());
+}
+
+#[test]
+fn out_of_reach() {
+    let address = serve(Mode::Cooperative);
+
+    // A move at the ends of the coordinates is turned down, rather than
+    // taking the server down with it
+    let stream = std::net::TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let line = format!("hello eve\ntouch {} 0\nflag 0 {}\n", isize::MAX, isize::MIN);
+    std::io::Write::write_all(&mut &stream, line.as_bytes()).unwrap();
+
+    let errors = std::io::BufRead::lines(std::io::BufReader::new(&stream))
+        .map(Result::unwrap)
+        .filter(|line| line.starts_with("error "))
+        .take(2)
+        .count();
+    assert_eq!(errors, 2);
+
+    let (mut ann, mut ann_game) = join(address, "ann");
+    ann.touch(Coord(0, 0));
+    sync_until(&mut ann, &mut ann_game, |client, game| {
+        game.get_chunk(Coord(0, 0)).is_some() && client.players().len() == 2
+    });
+}
+
+#[test]
+fn stalled_connections() {
+    let address = serve(Mode::Cooperative);
+    let (mut ann, mut ann_game) = join(address, "ann");
+    for x in -2..3 {
+        ann.touch(Coord(x * 8 + 3, 3));
+    }
+    sync_until(&mut ann, &mut ann_game, |_, game| game.get_chunk(Coord(2, 0)).is_some());
+
+    // Asks for a few dozen chunks over and over without ever reading them,
+    // until there's more to send than the connection can hold
+    let mut stalled = std::net::TcpStream::connect(address).unwrap();
+    let mut requests = stalled.try_clone().unwrap();
+    thread::spawn(move || {
+        let lines = format!("hello stalled\n{}", "view -3 -1 4 2\nview 100 100 101 101\n".repeat(50_000));
+        let _ = std::io::Write::write_all(&mut requests, lines.as_bytes());
+    });
+
+    // Rather than holding everyone else up, it's dropped
+    let mut arrived = false;
+    sync_until(&mut ann, &mut ann_game, |client, _| {
+        let here = client.players().values().any(|player| player.name == "stalled");
+        arrived |= here;
+        arrived && !here
+    });
+    let (mut bob, mut bob_game) = join(address, "bob");
+    sync_until(&mut bob, &mut bob_game, |client, _| client.players().len() == 2);
+
+    stalled.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    match std::io::Read::read_to_end(&mut stalled, &mut Vec::new()) {
+        Ok(_) => (),
+        Err(error) => assert_eq!(error.kind(), std::io::ErrorKind::ConnectionReset),
+    }
+    let _ = stalled.shutdown(std::net::Shutdown::Both);
+}
+
+#[test]
+fn race() {
+    let address = serve(Mode::Race);
+    let mut expected = Game::with_settings(SEED, Difficulty::Normal);
+
+    let (mut ann, mut ann_game) = join(address, "ann");
+    let (mut bob, mut bob_game) = join(address, "bob");
+    assert_eq!(bob.mode(), Mode::Race);
+
+    ann.touch(Coord(3, 3));
+    play(&mut expected, Coord(3, 3));
+
+    // Ann clears the chunk that opened up, all but one mine
+    let opened = &expected.chunks[&Coord(0, 0)];
+    let unclicked = chunk::all_squares().filter(|&square| !opened.clicked.get(square));
+    let (mines, safe): (Vec<_>, Vec<_>) = unclicked.partition(|&square| opened.mines.get(square));
+    let world = |square| Coord::from(AbsoluteCoord { chunk: Coord(0, 0), square });
+
+    for &square in safe.iter() {
+        ann.touch(world(square));
+        play(&mut expected, world(square));
+    }
+    for &square in mines[1..].iter() {
+        ann.toggle_flag(world(square));
+        expected.toggle_flag(world(square));
+    }
+    sync_until(&mut ann, &mut ann_game, |_, game| mirrors(game, &expected));
+
+    // Then both go for the last one. Whoever's flag arrives first wins the
+    // chunk, and the other's lands on a finished chunk and does nothing.
+    ann.toggle_flag(world(mines[0]));
+    bob.toggle_flag(world(mines[0]));
+    expected.toggle_flag(world(mines[0]));
+    assert_eq!(expected.chunks_won(), 1);
+
+    let (ann_id, bob_id) = (ann.id(), bob.id());
+    sync_until(&mut bob, &mut bob_game, |client, game| {
+        let solved = |id| client.players().get(&id).map_or(0, |player| player.solved);
+        mirrors(game, &expected) && solved(ann_id) + solved(bob_id) == 1
+    });
+
+    // Exploding a chunk is a penalty for whoever did it
+    let east = &expected.chunks[&Coord(1, 0)];
+    let mine = chunk::all_squares().find(|&square| east.mines.get(square)).unwrap();
+    bob.touch(Coord::from(AbsoluteCoord { chunk: Coord(1, 0), square: mine }));
+    sync_until(&mut ann, &mut ann_game, |client, _| {
+        client.players().get(&bob_id).is_some_and(|bob| bob.exploded == 1)
+    });
+    assert_eq!(ann.players()[&ann_id].exploded, 0);
+    assert_eq!((ann_game.chunks_won(), ann_game.chunks_lost()), (1, 1));
+}
+
+#[test]
+fn spectators() {
+    let address = serve(Mode::Cooperative);
+    let mut expected = Game::with_settings(SEED, Difficulty::Normal);
+
+    let (mut ann, mut ann_game) = join(address, "ann");
+    let mut watcher = Client::watch(address).unwrap();
+    let mut watched = watcher.game();
+    watcher.view(VIEW.0, VIEW.1);
+
+    // Connections that don't say hello can't change anything
+    let mut sneaky = std::net::TcpStream::connect(address).unwrap();
+    std::io::Write::write_all(&mut sneaky, b"view -2 -2 3 3\ntouch -12 -12\nflag -11 -12\n").unwrap();
+    watcher.touch(Coord(-4, 12));
+
+    ann.touch(Coord(3, 3));
+    ann.move_cursor(Coord(1, 1));
+    play(&mut expected, Coord(3, 3));
+
+    let ann_id = ann.id();
+    sync_until(&mut watcher, &mut watched, |client, game| {
+        mirrors(game, &expected) && client.cursor_at(Coord(1, 1)) == Some(ann_id)
+    });
+    sync_until(&mut ann, &mut ann_game, |_, game| mirrors(game, &expected));
+
+    // Nor does anyone see them
+    assert_eq!(ann.players().len(), 1);
+    assert_eq!(watcher.players().len(), 1);
+}
@@ -0,0 +1,9 @@
+// Cooperative play over TCP. A `Server` owns the game and `Client`s send it
+// their actions, getting back the chunks they're looking at as they change.
+
+pub mod protocol;
+pub mod server;
+pub mod client;
+
+#[cfg(test)]
+pub mod localhost;
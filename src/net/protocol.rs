@@ -0,0 +1,284 @@
+// One message per line of text, a word naming it followed by its fields, so
+// a session can be followed (or faked) with a plain TCP client. Players
+// start with a hello; connections that don't are spectators, who are sent
+// everything but can't act.
+
+use crate::{
+    aux::coord::Coord,
+    game::{SquareView, chunk::{self, Difficulty, Outcome}},
+};
+
+use std::str::SplitWhitespace;
+
+pub use crate::game::PlayerId;
+
+// What players are after. Everyone plays the same way either way; it's
+// about what counts.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mode {
+    // Clearing as much of the world as possible between everyone
+    Cooperative,
+    // Points for each chunk a player finishes first, a penalty for each
+    // one they explode
+    Race,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 2] = [Mode::Cooperative, Mode::Race];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Cooperative => "coop",
+            Mode::Race        => "race",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Mode::ALL.iter().cloned().find(|mode| mode.name() == name)
+    }
+}
+
+// Sent by players to the server
+#[derive(Clone, PartialEq, Debug)]
+pub enum Request {
+    Hello(String),
+    // Chunks in `min..max` are being looked at and should be kept up to date
+    View(Coord<isize>, Coord<isize>),
+    Touch(Coord<isize>),
+    Flag(Coord<isize>),
+    Cursor(Coord<isize>),
+}
+
+// Sent by the server to players
+#[derive(Clone, PartialEq, Debug)]
+pub enum Update {
+    Welcome { id: PlayerId, seed: u64, difficulty: Difficulty, mode: Mode },
+    Joined(PlayerId, String),
+    Left(PlayerId),
+    Cursor(PlayerId, Coord<isize>),
+    Chunk { coord: Coord<isize>, outcome: Option<Outcome>, views: Vec<SquareView> },
+    // Chunks each player has solved and exploded
+    Score { id: PlayerId, solved: u64, exploded: u64 },
+    // The same for the whole game, which outlives its players
+    Totals { won: u64, lost: u64 },
+    // Why a request was turned down
+    Error(String),
+}
+
+impl Request {
+    pub fn encode(&self) -> String {
+        match self {
+            Request::Hello(name) => format!("hello {}", name),
+            Request::View(min, max) => format!("view {} {}", coord(*min), coord(*max)),
+            Request::Touch(at)  => format!("touch {}", coord(*at)),
+            Request::Flag(at)   => format!("flag {}", coord(*at)),
+            Request::Cursor(at) => format!("cursor {}", coord(*at)),
+        }
+    }
+
+    pub fn decode(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+
+        let request = match words.next() {
+            Some("hello") => Request::Hello(name(&mut words)?),
+            Some("view") => {
+                let min = parse_coord(&mut words)?;
+                Request::View(min, parse_coord(&mut words)?)
+            },
+            Some("touch")  => Request::Touch(parse_coord(&mut words)?),
+            Some("flag")   => Request::Flag(parse_coord(&mut words)?),
+            Some("cursor") => Request::Cursor(parse_coord(&mut words)?),
+            _ => return Err(format!("Unknown request: {}", line)),
+        };
+
+        finish(words, request)
+    }
+}
+
+impl Update {
+    pub fn encode(&self) -> String {
+        match self {
+            Update::Welcome { id, seed, difficulty, mode } =>
+                format!("welcome {} {} {} {}", id, seed, difficulty.name(), mode.name()),
+            Update::Joined(id, name) => format!("joined {} {}", id, name),
+            Update::Left(id) => format!("left {}", id),
+            Update::Cursor(id, at) => format!("cursor {} {}", id, coord(*at)),
+            Update::Chunk { coord: at, outcome, views } => {
+                let outcome = match outcome {
+                    None => "-",
+                    Some(Outcome::Won)  => "won",
+                    Some(Outcome::Lost) => "lost",
+                };
+                let views: String = views.iter().map(|&view| view_char(view)).collect();
+                format!("chunk {} {} {}", coord(*at), outcome, views)
+            },
+            Update::Score { id, solved, exploded } => format!("score {} {} {}", id, solved, exploded),
+            Update::Totals { won, lost } => format!("totals {} {}", won, lost),
+            Update::Error(message) => format!("error {}", message),
+        }
+    }
+
+    pub fn decode(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+
+        let update = match words.next() {
+            Some("welcome") => {
+                let id = number(&mut words)?;
+                let seed = number(&mut words)?;
+                let difficulty = words.next()
+                    .and_then(Difficulty::from_name)
+                    .ok_or("Invalid difficulty")?;
+                let mode = words.next()
+                    .and_then(Mode::from_name)
+                    .ok_or("Invalid mode")?;
+                Update::Welcome { id, seed, difficulty, mode }
+            },
+            Some("joined") => {
+                let id = number(&mut words)?;
+                Update::Joined(id, name(&mut words)?)
+            },
+            Some("left") => Update::Left(number(&mut words)?),
+            Some("cursor") => {
+                let id = number(&mut words)?;
+                Update::Cursor(id, parse_coord(&mut words)?)
+            },
+            Some("chunk") => {
+                let at = parse_coord(&mut words)?;
+                let outcome = match words.next() {
+                    Some("-")    => None,
+                    Some("won")  => Some(Outcome::Won),
+                    Some("lost") => Some(Outcome::Lost),
+                    _ => return Err("Invalid outcome".to_owned()),
+                };
+                let views = words.next()
+                    .ok_or("Missing squares")?
+                    .chars()
+                    .map(char_view)
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|views| views.len() == chunk::DIMENSION * chunk::DIMENSION)
+                    .ok_or("Invalid squares")?;
+                Update::Chunk { coord: at, outcome, views }
+            },
+            Some("score") => {
+                let id = number(&mut words)?;
+                let solved = number(&mut words)?;
+                Update::Score { id, solved, exploded: number(&mut words)? }
+            },
+            Some("totals") => {
+                let won = number(&mut words)?;
+                Update::Totals { won, lost: number(&mut words)? }
+            },
+            // The rest of the line, however many words it is
+            Some("error") => return Ok(Update::Error(words.collect::<Vec<_>>().join(" "))),
+            _ => return Err(format!("Unknown update: {}", line)),
+        };
+
+        finish(words, update)
+    }
+}
+
+fn coord(Coord(x, y): Coord<isize>) -> String {
+    format!("{} {}", x, y)
+}
+
+fn number<T: std::str::FromStr>(words: &mut SplitWhitespace) -> Result<T, String> {
+    let word = words.next().ok_or("Missing number")?;
+    word.parse().map_err(|_| format!("Invalid number: {}", word))
+}
+
+fn parse_coord(words: &mut SplitWhitespace) -> Result<Coord<isize>, String> {
+    let x = number(words)?;
+    Ok(Coord(x, number(words)?))
+}
+
+// Names are a single word so they can't run into other fields
+fn name(words: &mut SplitWhitespace) -> Result<String, String> {
+    words.next().map(str::to_owned).ok_or_else(|| "Missing name".to_owned())
+}
+
+fn finish<T>(mut words: SplitWhitespace, message: T) -> Result<T, String> {
+    match words.next() {
+        Some(extra) => Err(format!("Unexpected {}", extra)),
+        None => Ok(message),
+    }
+}
+
+// Also how bots see squares
+pub fn view_char(view: SquareView) -> char {
+    match view {
+        SquareView::Clicked(count) => (b'0' + count) as char,
+        SquareView::Unclicked => '#',
+        SquareView::Flagged   => 'f',
+        SquareView::Penalty   => '*',
+        SquareView::Points    => '$',
+    }
+}
+
+fn char_view(ch: char) -> Option<SquareView> {
+    Some(match ch {
+        '0'..='8' => SquareView::Clicked(ch as u8 - b'0'),
+        '#' => SquareView::Unclicked,
+        'f' => SquareView::Flagged,
+        '*' => SquareView::Penalty,
+        '$' => SquareView::Points,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let requests = [
+            Request::Hello("ann".to_owned()),
+            Request::View(Coord(-3, -2), Coord(4, 1)),
+            Request::Touch(Coord(17, -40)),
+            Request::Flag(Coord(0, 0)),
+            Request::Cursor(Coord(-1, 9)),
+        ];
+        for request in requests.iter() {
+            assert_eq!(Request::decode(&request.encode()).as_ref(), Ok(request));
+        }
+
+        let mut views = vec![SquareView::Unclicked; 64];
+        views[0] = SquareView::Clicked(0);
+        views[9] = SquareView::Clicked(8);
+        views[10] = SquareView::Flagged;
+        views[62] = SquareView::Penalty;
+        views[63] = SquareView::Points;
+
+        let updates = [
+            Update::Welcome { id: 3, seed: u64::MAX, difficulty: Difficulty::Hard, mode: Mode::Race },
+            Update::Joined(3, "bob".to_owned()),
+            Update::Left(3),
+            Update::Cursor(1, Coord(-5, 5)),
+            Update::Chunk { coord: Coord(2, -7), outcome: Some(Outcome::Lost), views },
+            Update::Score { id: 2, solved: 10, exploded: 1 },
+            Update::Totals { won: 11, lost: 4 },
+            Update::Error("Out of reach: 1 2".to_owned()),
+        ];
+        for update in updates.iter() {
+            assert_eq!(Update::decode(&update.encode()).as_ref(), Ok(update));
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        for line in ["", "touch", "touch 1", "touch 1 x", "touch 1 2 3", "hello", "jump 1 2"].iter() {
+            assert!(Request::decode(line).is_err(), "{}", line);
+        }
+
+        let squares = "#".repeat(64);
+        assert!(Update::decode(&format!("chunk 0 0 - {}", squares)).is_ok());
+        assert!(Update::decode(&format!("chunk 0 0 - {}#", squares)).is_err());
+        assert!(Update::decode(&format!("chunk 0 0 - {}", &squares[1..])).is_err());
+        assert!(Update::decode(&format!("chunk 0 0 tied {}", squares)).is_err());
+        assert!(Update::decode(&format!("chunk 0 0 - 9{}", &squares[1..])).is_err());
+        assert!(Update::decode("welcome 1 2 impossible coop").is_err());
+        assert!(Update::decode("welcome 1 2 easy solo").is_err());
+        assert!(Update::decode("welcome 1 2 easy").is_err());
+        assert!(Update::decode("score 1 -2 3").is_err());
+    }
+}
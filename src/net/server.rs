@@ -0,0 +1,340 @@
+use super::protocol::{Mode, PlayerId, Request, Update};
+use crate::{
+    aux::{coord::Coord, index_iter::IndexIterSigned},
+    game::{Game, AbsoluteCoord, within_reach, chunk, event::Event},
+};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, Sender, SyncSender},
+    thread,
+    time::Duration,
+};
+
+// Bigger views than this many chunks across are cut down, so nobody can
+// ask for the whole world at once
+const MAX_VIEW_CHUNKS: isize = 64;
+// Lines waiting to go out to one player. Enough for a whole view, past
+// which they're taken to have stopped reading and are dropped.
+const MAX_QUEUED_LINES: usize = 4 * (MAX_VIEW_CHUNKS * MAX_VIEW_CHUNKS) as usize;
+// How long to wait after a connection can't be accepted
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+enum Incoming {
+    Joined(TcpStream, SyncSender<String>),
+    Request(Request),
+    Left,
+}
+
+struct Player {
+    stream: TcpStream,
+    outbox: SyncSender<String>,
+    name: Option<String>,
+    view: Option<(Coord<isize>, Coord<isize>)>,
+    cursor: Option<Coord<isize>>,
+    solved: u64,
+    exploded: u64,
+}
+
+impl Player {
+    fn sees(&self, coord: Coord<isize>) -> bool {
+        self.view.is_some_and(|view| in_view(view, coord))
+    }
+
+    // Never waits on the connection. Returns false, cutting the connection
+    // off, when the player has fallen too far behind or can't be written to.
+    fn queue(&self, line: String) -> bool {
+        let queued = self.outbox.try_send(line).is_ok();
+        if !queued {
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+        queued
+    }
+}
+
+// Owns the one true game, applying everyone's actions to it in the order
+// they arrive and sending back whatever they changed. That order settles
+// any race: whoever's action arrives first finishes a chunk, and actions on
+// it after that do nothing.
+pub struct Server {
+    listener: TcpListener,
+    game: Game,
+    mode: Mode,
+    players: BTreeMap<PlayerId, Player>,
+    changes: Receiver<Event>,
+}
+
+impl Server {
+    pub fn bind(address: impl ToSocketAddrs, mut game: Game) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let changes = game.subscribe();
+        Ok(Server { listener, game, mode: Mode::Cooperative, players: BTreeMap::new(), changes })
+    }
+
+    // Cooperative unless told otherwise
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Serves players for as long as the server is up
+    pub fn run(mut self) -> io::Result<()> {
+        let (sender, inbox) = mpsc::channel();
+        let listener = self.listener.try_clone()?;
+        let accepting = thread::spawn(move || accept(listener, sender));
+
+        for (id, incoming) in inbox {
+            match incoming {
+                Incoming::Joined(stream, outbox) => self.join(id, stream, outbox),
+                Incoming::Request(request) => self.handle(id, request),
+                Incoming::Left => self.leave(id),
+            }
+        }
+
+        if let Err(panic) = accepting.join() {
+            std::panic::resume_unwind(panic);
+        }
+        Ok(())
+    }
+
+    fn join(&mut self, id: PlayerId, stream: TcpStream, outbox: SyncSender<String>) {
+        let player = Player { stream, outbox, name: None, view: None, cursor: None, solved: 0, exploded: 0 };
+        self.players.insert(id, player);
+
+        let (seed, difficulty, mode) = (self.game.seed(), self.game.difficulty(), self.mode);
+        self.send(id, &Update::Welcome { id, seed, difficulty, mode });
+        self.send(id, &Update::Totals { won: self.game.chunks_won(), lost: self.game.chunks_lost() });
+
+        // Catch up on everyone already here
+        let others: Vec<_> = self.players
+            .iter()
+            .filter(|&(&other, _)| other != id)
+            .filter_map(|(&other, player)| player.name.clone().map(|name| (other, name, player)))
+            .flat_map(|(other, name, player)| {
+                let score = Update::Score { id: other, solved: player.solved, exploded: player.exploded };
+                let cursor = player.cursor.map(|at| Update::Cursor(other, at));
+                vec![Update::Joined(other, name), score].into_iter().chain(cursor)
+            })
+            .collect();
+        for update in others.iter() {
+            self.send(id, update);
+        }
+    }
+
+    fn leave(&mut self, id: PlayerId) {
+        let player = self.players.remove(&id);
+        if player.is_some_and(|player| player.name.is_some()) {
+            self.broadcast(&Update::Left(id));
+        }
+    }
+
+    fn handle(&mut self, id: PlayerId, request: Request) {
+        let playing = self.players.get(&id).is_some_and(|player| player.name.is_some());
+
+        match request {
+            // Saying hello again just changes the name
+            Request::Hello(name) => {
+                let player = match self.players.get_mut(&id) {
+                    Some(player) => player,
+                    None => return,
+                };
+                player.name = Some(name.clone());
+                let score = Update::Score { id, solved: player.solved, exploded: player.exploded };
+                self.broadcast(&Update::Joined(id, name));
+                self.broadcast(&score);
+            },
+            Request::View(min, max) => self.view(id, min, max),
+            // Spectators never say hello, and only get to watch
+            _ if !playing => (),
+            Request::Touch(at) | Request::Flag(at) | Request::Cursor(at) if !within_reach(at) => {
+                self.send(id, &Update::Error(format!("Out of reach: {} {}", at.0, at.1)));
+            },
+            Request::Touch(at) => {
+                self.game.act_as(Some(id));
+                if self.game.touch(&[at]) {
+                    while self.game.spread() {}
+                }
+                self.publish();
+            },
+            Request::Flag(at) => {
+                self.game.act_as(Some(id));
+                self.game.toggle_flag(at);
+                self.publish();
+            },
+            Request::Cursor(at) => {
+                if let Some(player) = self.players.get_mut(&id) { player.cursor = Some(at); }
+                self.broadcast(&Update::Cursor(id, at));
+            },
+        }
+    }
+
+    // Sends the chunks that just came into view. Views reaching past the
+    // ends of the coordinates are ignored.
+    fn view(&mut self, id: PlayerId, min: Coord<isize>, max: Coord<isize>) {
+        let side = |min: isize, max: isize| max
+            .checked_sub(min)
+            .map(|side| side.clamp(0, MAX_VIEW_CHUNKS))
+            .filter(|&side| min.checked_add(side).is_some());
+        let size = match (side(min.0, max.0), side(min.1, max.1)) {
+            (Some(width), Some(height)) => Coord(width, height),
+            _ => return,
+        };
+        let max = min + size;
+        let player = match self.players.get_mut(&id) {
+            Some(player) => player,
+            None => return,
+        };
+
+        let before = player.view.replace((min, max));
+
+        let updates: Vec<_> = IndexIterSigned::new(size, min)
+            .filter(|&coord| !before.is_some_and(|view| in_view(view, coord)))
+            .filter_map(|coord| self.chunk_update(coord))
+            .collect();
+        for update in updates.iter() {
+            self.send(id, update);
+        }
+    }
+
+    // Sends out what the last action changed, crediting each chunk it
+    // finished to whoever the game says finished it
+    fn publish(&mut self) {
+        let mut changed = Vec::new();
+        let mut scorers = Vec::new();
+        let mut finished = false;
+
+        for event in self.changes.try_iter() {
+            let (coord, won) = match event {
+                Event::SquareRevealed(at) | Event::FlagToggled(at) => {
+                    changed.push(AbsoluteCoord::from(at).chunk);
+                    continue;
+                },
+                Event::ChunkWon(coord)  => (coord, true),
+                Event::ChunkLost(coord) => (coord, false),
+                Event::ChunkAllocated(_) | Event::ScoreChanged(_) => continue,
+            };
+            changed.push(coord);
+            finished = true;
+
+            let owner = self.game.owner(coord);
+            let players = &mut self.players;
+            if let Some(player) = owner.and_then(|owner| players.get_mut(&owner)) {
+                if won { player.solved += 1; } else { player.exploded += 1; }
+                scorers.extend(owner);
+            }
+        }
+        changed.sort_by_key(|&Coord(x, y)| (y, x));
+        changed.dedup();
+
+        for coord in changed {
+            let update = match self.chunk_update(coord) {
+                Some(update) => update,
+                None => continue,
+            };
+            let viewers: Vec<_> = self.players
+                .iter()
+                .filter(|(_, player)| player.sees(coord))
+                .map(|(&viewer, _)| viewer)
+                .collect();
+            for viewer in viewers {
+                self.send(viewer, &update);
+            }
+        }
+
+        if !finished { return; }
+
+        scorers.dedup();
+        for id in scorers {
+            // Unless they were just dropped for falling behind
+            let score = match self.players.get(&id) {
+                Some(player) => Update::Score { id, solved: player.solved, exploded: player.exploded },
+                None => continue,
+            };
+            self.broadcast(&score);
+        }
+        self.broadcast(&Update::Totals { won: self.game.chunks_won(), lost: self.game.chunks_lost() });
+    }
+
+    fn chunk_update(&self, coord: Coord<isize>) -> Option<Update> {
+        let chunk = self.game.get_chunk(coord)?;
+        let views = chunk::all_squares().map(|square| chunk.view(square)).collect();
+        Some(Update::Chunk { coord, outcome: chunk.outcome(), views })
+    }
+
+    fn send(&mut self, id: PlayerId, update: &Update) {
+        if self.players.get(&id).is_some_and(|player| !player.queue(update.encode())) {
+            self.leave(id);
+        }
+    }
+
+    fn broadcast(&mut self, update: &Update) {
+        let line = update.encode();
+        let behind: Vec<_> = self.players
+            .iter()
+            .filter(|(_, player)| !player.queue(line.clone()))
+            .map(|(&id, _)| id)
+            .collect();
+        for id in behind {
+            self.leave(id);
+        }
+    }
+}
+
+fn in_view((min, max): (Coord<isize>, Coord<isize>), Coord(x, y): Coord<isize>) -> bool {
+    min.0 <= x && x < max.0 && min.1 <= y && y < max.1
+}
+
+// A connection that fails only loses that player, and the next one is
+// accepted after a moment, in case the server ran out of file handles
+fn accept(listener: TcpListener, inbox: Sender<(PlayerId, Incoming)>) {
+    for (id, stream) in (1..).zip(listener.incoming()) {
+        let streams = stream.and_then(|stream| Ok((BufReader::new(stream.try_clone()?), stream.try_clone()?, stream)));
+        let (reader, writer, stream) = match streams {
+            Ok(streams) => streams,
+            Err(_) => {
+                thread::sleep(ACCEPT_RETRY);
+                continue;
+            },
+        };
+        let _ = stream.set_nodelay(true);
+
+        let (outbox, lines) = mpsc::sync_channel(MAX_QUEUED_LINES);
+        thread::spawn(move || write(writer, lines));
+
+        if inbox.send((id, Incoming::Joined(stream, outbox))).is_err() { break; }
+
+        let inbox = inbox.clone();
+        thread::spawn(move || {
+            // Lines that make no sense are skipped rather than ending the session
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if let Ok(request) = Request::decode(&line) {
+                    if inbox.send((id, Incoming::Request(request))).is_err() { return; }
+                }
+            }
+            let _ = inbox.send((id, Incoming::Left));
+        });
+    }
+}
+
+// Writes whatever is queued, flushing whenever the queue runs dry
+fn write(stream: TcpStream, lines: Receiver<String>) -> io::Result<()> {
+    let mut writer = BufWriter::new(stream);
+
+    while let Ok(line) = lines.recv() {
+        writeln!(writer, "{}", line)?;
+        for line in lines.try_iter() {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}